@@ -8,18 +8,126 @@
 #include <stdint.h>
 #include <stdlib.h>
 
-#ifdef __cplusplus
-extern "C" {
-#endif
+#define MAX_DRAW_DESCRIPTOR_SETS 4
+
+#define SHADER_HASH_SIZE 32
+
+typedef enum Index_type {
+  U16 = 0,
+  U32 = 1,
+} Index_type;
+
+typedef enum Pipeline_status {
+  Pending = 0,
+  Ready = 1,
+  Failed = 2,
+} Pipeline_status;
+
+typedef enum Shader_stage {
+  Vertex = 0,
+  Fragment = 1,
+  Compute = 2,
+  Geometry = 3,
+  Tess_control = 4,
+  Tess_evaluation = 5,
+  Task = 6,
+  Mesh = 7,
+  Ray_generation = 8,
+  Any_hit = 9,
+  Closest_hit = 10,
+  Miss = 11,
+  Intersection = 12,
+  Callable = 13,
+} Shader_stage;
 
 typedef enum Status_code {
   Success = 0,
   Failure = 1,
 } Status_code;
 
+typedef enum Vertex_format {
+  Float = 0,
+  Vec2 = 1,
+  Vec3 = 2,
+  Vec4 = 3,
+  Int = 4,
+  Ivec2 = 5,
+  Ivec3 = 6,
+  Ivec4 = 7,
+  Uint = 8,
+  Uvec2 = 9,
+  Uvec3 = 10,
+  Uvec4 = 11,
+  Unorm8x4 = 12,
+} Vertex_format;
+
+typedef struct Logger Logger;
+
 typedef struct Vulkan_application Vulkan_application;
 
-void log_init(const char *log_file, unsigned int log_level);
+typedef struct Vulkan_prewarm Vulkan_prewarm;
+
+typedef struct Memory_stats {
+  uint64_t block_count;
+  uint64_t allocation_count;
+  uint64_t reserved_bytes;
+  uint64_t used_bytes;
+} Memory_stats;
+
+typedef struct Raw_vertex_attribute {
+  uint32_t location;
+  uint32_t format;
+  uint32_t offset;
+} Raw_vertex_attribute;
+
+typedef uint64_t Pipeline_handle;
+
+typedef uint64_t Vertex_buffer_handle;
+
+typedef uint64_t Index_buffer_handle;
+
+typedef uint64_t Descriptor_set_handle;
+
+typedef struct Draw_command {
+  Pipeline_handle pipeline;
+  Vertex_buffer_handle vertex_buffer;
+  Index_buffer_handle index_buffer;
+  Vertex_buffer_handle instance_buffer;
+  uint32_t first;
+  uint32_t count;
+  int32_t vertex_offset;
+  uint32_t first_instance;
+  uint32_t instance_count;
+  Descriptor_set_handle descriptor_sets[MAX_DRAW_DESCRIPTOR_SETS];
+  uint32_t push_constant_offset;
+  uint32_t push_constant_size;
+} Draw_command;
+
+#define Pipeline_handle_DEFAULT 0
+
+#define Vertex_buffer_handle_DEFAULT 0
+
+#define Vertex_buffer_handle_NONE UINT64_MAX
+
+#define Index_buffer_handle_NONE UINT64_MAX
+
+#define Descriptor_set_handle_NONE UINT64_MAX
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+enum Status_code log_init(const char *log_file, unsigned long long log_level);
+
+enum Status_code get_logger(unsigned long long log_level,
+                            unsigned long long logger_size,
+                            struct Logger **logger);
+
+enum Status_code get_last_message_length(const struct Logger *logger, unsigned long long *length);
+
+enum Status_code get_last_message(struct Logger *logger, char *data);
+
+void destroy_logger(struct Logger *logger);
 
 enum Status_code get_vulkan_application(void *window_handle,
                                         unsigned int width,
@@ -27,12 +135,158 @@ enum Status_code get_vulkan_application(void *window_handle,
                                         const char *vulkan_path,
                                         struct Vulkan_application **vulkan_application);
 
+enum Status_code get_vulkan_application_with_cache(void *window_handle,
+                                                   unsigned int width,
+                                                   unsigned int height,
+                                                   const char *vulkan_path,
+                                                   const char *pipeline_cache_path,
+                                                   struct Vulkan_application **vulkan_application);
+
+enum Status_code prewarm_vulkan_application(const char *vulkan_path,
+                                            const char *pipeline_cache_path,
+                                            const char *pipeline_manifest_path,
+                                            struct Vulkan_prewarm **vulkan_prewarm);
+
+enum Status_code is_prewarm_finished(const struct Vulkan_prewarm *vulkan_prewarm, bool *finished);
+
+enum Status_code finish_prewarm(struct Vulkan_prewarm *vulkan_prewarm,
+                                struct Vulkan_application **vulkan_application);
+
+enum Status_code attach_surface(struct Vulkan_application *vulkan_application,
+                                void *window_handle,
+                                unsigned int width,
+                                unsigned int height);
+
+enum Status_code save_pipeline_cache(const struct Vulkan_application *vulkan_application);
+
+enum Status_code load_pipeline_manifest(struct Vulkan_application *vulkan_application,
+                                        const char *pipeline_manifest_path);
+
+enum Status_code get_embedded_shader(const char *name,
+                                     const uint8_t **data,
+                                     unsigned long long *size,
+                                     enum Shader_stage *stage);
+
+enum Status_code get_embedded_shader_variant(const char *name,
+                                             const char *const *features,
+                                             unsigned long long feature_count,
+                                             const uint8_t **data,
+                                             unsigned long long *size,
+                                             enum Shader_stage *stage);
+
+enum Status_code register_shader(struct Vulkan_application *vulkan_application,
+                                 const char *name,
+                                 const uint8_t *data,
+                                 unsigned long long size);
+
+enum Status_code register_shader_file(struct Vulkan_application *vulkan_application,
+                                      const char *name,
+                                      const char *path);
+
+enum Status_code open_shader_store(struct Vulkan_application *vulkan_application, const char *path);
+
+enum Status_code store_shader(struct Vulkan_application *vulkan_application,
+                              const uint8_t *data,
+                              unsigned long long size,
+                              uint8_t *hash);
+
+enum Status_code get_pipeline_handle(const struct Vulkan_application *vulkan_application,
+                                     const char *name,
+                                     unsigned long long *pipeline);
+
+enum Status_code wait_pipelines(const struct Vulkan_application *vulkan_application,
+                                unsigned long long *failed_count);
+
+enum Status_code get_pipeline_status(const struct Vulkan_application *vulkan_application,
+                                     unsigned long long pipeline,
+                                     enum Pipeline_status *status);
+
+enum Status_code wait_pipeline(const struct Vulkan_application *vulkan_application,
+                               unsigned long long pipeline,
+                               enum Pipeline_status *status);
+
+enum Status_code get_memory_stats(const struct Vulkan_application *vulkan_application,
+                                  struct Memory_stats *stats);
+
+enum Status_code create_vertex_buffer(struct Vulkan_application *vulkan_application,
+                                      const uint8_t *data,
+                                      unsigned long long size,
+                                      unsigned int stride,
+                                      const struct Raw_vertex_attribute *attributes,
+                                      unsigned long long attribute_count,
+                                      unsigned long long *vertex_buffer);
+
+enum Status_code destroy_vertex_buffer(struct Vulkan_application *vulkan_application,
+                                       unsigned long long vertex_buffer);
+
+enum Status_code create_index_buffer(struct Vulkan_application *vulkan_application,
+                                     const uint8_t *data,
+                                     unsigned long long size,
+                                     unsigned int index_type,
+                                     unsigned long long *index_buffer);
+
+enum Status_code destroy_index_buffer(struct Vulkan_application *vulkan_application,
+                                      unsigned long long index_buffer);
+
+enum Status_code create_uniform_buffer(struct Vulkan_application *vulkan_application,
+                                       unsigned long long size,
+                                       unsigned long long *uniform_buffer);
+
+enum Status_code update_uniform_buffer(struct Vulkan_application *vulkan_application,
+                                       unsigned long long uniform_buffer,
+                                       const uint8_t *data,
+                                       unsigned long long size);
+
+enum Status_code destroy_uniform_buffer(struct Vulkan_application *vulkan_application,
+                                        unsigned long long uniform_buffer);
+
+enum Status_code create_descriptor_set(struct Vulkan_application *vulkan_application,
+                                       unsigned long long pipeline,
+                                       unsigned int set,
+                                       unsigned long long *descriptor_set);
+
+enum Status_code bind_uniform_buffer(struct Vulkan_application *vulkan_application,
+                                     unsigned long long descriptor_set,
+                                     unsigned int binding,
+                                     unsigned long long uniform_buffer);
+
+enum Status_code destroy_descriptor_set(struct Vulkan_application *vulkan_application,
+                                        unsigned long long descriptor_set);
+
+enum Status_code load_texture_file(struct Vulkan_application *vulkan_application,
+                                   const char *path,
+                                   unsigned long long *texture);
+
+enum Status_code load_texture(struct Vulkan_application *vulkan_application,
+                              const uint8_t *data,
+                              unsigned long long size,
+                              unsigned long long *texture);
+
+enum Status_code get_texture_size(const struct Vulkan_application *vulkan_application,
+                                  unsigned long long texture,
+                                  unsigned int *width,
+                                  unsigned int *height);
+
+enum Status_code bind_texture(struct Vulkan_application *vulkan_application,
+                              unsigned long long descriptor_set,
+                              unsigned int binding,
+                              unsigned long long texture);
+
+enum Status_code destroy_texture(struct Vulkan_application *vulkan_application,
+                                 unsigned long long texture);
+
 enum Status_code draw_frame(struct Vulkan_application *vulkan_application);
 
+enum Status_code draw_frame_with_commands(struct Vulkan_application *vulkan_application,
+                                          const struct Draw_command *draw_commands,
+                                          unsigned long long draw_count,
+                                          const uint8_t *push_constants,
+                                          unsigned long long push_constant_size);
+
 void destroy_vulkan_application(struct Vulkan_application *vulkan_application);
 
 #ifdef __cplusplus
 }
 #endif
 
-#endif  /* RUST_VULKAN_LIB_H */
\ No newline at end of file
+#endif  /* RUST_VULKAN_LIB_H */
@@ -1,40 +1,272 @@
 //Made by Han_feng
 
+#![allow(non_camel_case_types)]
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 use std::{env, fs};
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use shaderc::{CompileOptions, Compiler, OptimizationLevel, ShaderKind};
+use serde::{Deserialize, Serialize};
+use shaderc::{CompileOptions, Compiler, EnvVersion, IncludeCallbackResult, IncludeType, OptimizationLevel, ResolvedInclude, ShaderKind, SourceLanguage, SpirvVersion, TargetEnv};
+use spirv_tools::val::Validator;
+
+const SHADER_DIR: &str = "shaders";
+const SHADER_INCLUDE_DIR: &str = "shaders/include";
+//Files in the shader directory that are not shaders themselves
+//A .glsl or .hlsl file is always meant as a shader, one without a stage is warned about
+const NON_SHADER_EXTENSIONS: &[&str] = &["hlsli", "h", "json"];
+//Every combination is compiled, so keep the variant count bounded
+const MAX_SHADER_FEATURES: usize = 8;
+
+const SPIRV_MAGIC: u32 = 0x07230203;
+const OP_ENTRY_POINT: u32 = 15;
+
+//Name, compile environment, validation environment and the highest SPIR-V minor version it accepts
+type Target_env_info = (&'static str, EnvVersion, spirv_tools::TargetEnv, usize);
+
+//Selected through RUST_VULKAN_SHADER_TARGET_ENV
+const TARGET_ENVS: &[Target_env_info] = &[
+    ("vulkan1.0", EnvVersion::Vulkan1_0, spirv_tools::TargetEnv::Vulkan_1_0, 0),
+    ("vulkan1.1", EnvVersion::Vulkan1_1, spirv_tools::TargetEnv::Vulkan_1_1, 3),
+    ("vulkan1.2", EnvVersion::Vulkan1_2, spirv_tools::TargetEnv::Vulkan_1_2, 5),
+    ("vulkan1.3", EnvVersion::Vulkan1_3, spirv_tools::TargetEnv::Vulkan_1_3, 6),
+];
+//Selected through RUST_VULKAN_SHADER_SPIRV_VERSION, indexed by minor version
+const SPIRV_VERSIONS: &[(&str, SpirvVersion)] = &[
+    ("1.0", SpirvVersion::V1_0),
+    ("1.1", SpirvVersion::V1_1),
+    ("1.2", SpirvVersion::V1_2),
+    ("1.3", SpirvVersion::V1_3),
+    ("1.4", SpirvVersion::V1_4),
+    ("1.5", SpirvVersion::V1_5),
+    ("1.6", SpirvVersion::V1_6),
+];
+//Selected through RUST_VULKAN_SHADER_OPT
+const OPTIMIZATION_LEVELS: &[(&str, OptimizationLevel)] = &[
+    ("zero", OptimizationLevel::Zero),
+    ("size", OptimizationLevel::Size),
+    ("performance", OptimizationLevel::Performance),
+];
+//Mesh and ray tracing stages are only expressible from SPIR-V 1.4 on, which needs Vulkan 1.2
+const MODERN_TARGET_ENV: usize = 2;
+const MODERN_SPIRV_VERSION: usize = 4;
+
+//Extension, shader kind, registry stage and whether the stage needs SPIR-V 1.4
+type Shader_stage_info = (&'static str, ShaderKind, &'static str, bool);
+//Registry name, stage and source language of a shader file
+type Shader_source_info = (Option<String>, &'static Shader_stage_info, SourceLanguage);
+
+const SHADER_STAGES: &[Shader_stage_info] = &[
+    ("vert", ShaderKind::Vertex, "Vertex", false),
+    ("frag", ShaderKind::Fragment, "Fragment", false),
+    ("comp", ShaderKind::Compute, "Compute", false),
+    ("geom", ShaderKind::Geometry, "Geometry", false),
+    ("tesc", ShaderKind::TessControl, "Tess_control", false),
+    ("tese", ShaderKind::TessEvaluation, "Tess_evaluation", false),
+    ("task", ShaderKind::Task, "Task", true),
+    ("mesh", ShaderKind::Mesh, "Mesh", true),
+    ("rgen", ShaderKind::RayGeneration, "Ray_generation", true),
+    ("rahit", ShaderKind::AnyHit, "Any_hit", true),
+    ("rchit", ShaderKind::ClosestHit, "Closest_hit", true),
+    ("rmiss", ShaderKind::Miss, "Miss", true),
+    ("rint", ShaderKind::Intersection, "Intersection", true),
+    ("rcall", ShaderKind::Callable, "Callable", true),
+];
+
+//Read from <shader file>.json next to the shader, every field is optional
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Shader_description{
+    //A define without a value is passed as a bare #define
+    #[serde(default)]
+    defines: BTreeMap<String, Option<String>>,
+    //Each combination of these is compiled with the enabled ones defined as 1
+    #[serde(default)]
+    features: Vec<String>,
+    //Stage extension such as "frag", wins over the one in the file name
+    stage: Option<String>,
+    //Function compiled as the entry point, HLSL shaders rarely call it main
+    entry_point: Option<String>,
+}
+
+//Debug builds default to unoptimized shaders with debug info, release builds to optimized ones without
+#[derive(Clone, Copy)]
+struct Compile_profile{
+    optimization_level: usize,
+    debug_info: bool,
+    target_env: usize,
+    //None leaves the version to the target environment
+    spirv_version: Option<usize>,
+}
+
+//Written to OUT_DIR/rust_vulkan_shaders.json
+#[derive(Serialize)]
+struct Shader_manifest{
+    optimization_level: &'static str,
+    debug_info: bool,
+    target_env: &'static str,
+    spirv_version: Option<&'static str>,
+    shaders: Vec<Shader_report>,
+}
+
+#[derive(Serialize)]
+struct Shader_report{
+    name: String,
+    stage: &'static str,
+    features: Vec<String>,
+    source: PathBuf,
+    spirv: PathBuf,
+    entry_points: Vec<String>,
+    instruction_count: usize,
+    unoptimized_size: usize,
+    optimized_size: usize,
+}
+
+impl Compile_profile{
+    fn from_env() -> Result<Self, Box<dyn Error>>{
+        let debug_build = env::var("PROFILE").is_ok_and(|profile| profile == "debug");
+        let default_level = if debug_build { "zero" } else { "performance" };
+        let default_debug_info = debug_build || env::var_os("CARGO_FEATURE_SHADER_DEBUG").is_some();
+
+        let profile = Self{
+            optimization_level: get_env_option("RUST_VULKAN_SHADER_OPT", OPTIMIZATION_LEVELS.iter().map(|(name, _)| *name))?
+                .unwrap_or_else(|| OPTIMIZATION_LEVELS.iter().position(|(name, _)| *name == default_level).unwrap()),
+            debug_info: match env::var("RUST_VULKAN_SHADER_DEBUG").as_deref() {
+                Ok("1" | "true" | "on") => true,
+                Ok("0" | "false" | "off") => false,
+                Ok(value) => Err(format!("Unknown RUST_VULKAN_SHADER_DEBUG {}, expected 1 or 0", value))?,
+                Err(_) => default_debug_info,
+            },
+            target_env: get_env_option("RUST_VULKAN_SHADER_TARGET_ENV", TARGET_ENVS.iter().map(|(name, ..)| *name))?.unwrap_or(0),
+            spirv_version: get_env_option("RUST_VULKAN_SHADER_SPIRV_VERSION", SPIRV_VERSIONS.iter().map(|(name, _)| *name))?,
+        };
+
+        let (env_name, .., max_spirv_version) = TARGET_ENVS[profile.target_env];
+        if let Some(spirv_version) = profile.spirv_version && spirv_version > max_spirv_version {
+            Err(format!("SPIR-V {} is not consumable by {}", SPIRV_VERSIONS[spirv_version].0, env_name))?
+        }
+
+        Ok(profile)
+    }
+
+    //Raises the environment and version for stages that cannot be expressed in the selected ones
+    fn get_stage_target(&self, stage_info: &Shader_stage_info) -> (usize, Option<usize>){
+        let (.., modern) = stage_info;
+
+        match *modern {
+            true => (self.target_env.max(MODERN_TARGET_ENV), Some(self.spirv_version.unwrap_or(0).max(MODERN_SPIRV_VERSION))),
+            false => (self.target_env, self.spirv_version),
+        }
+    }
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
     //Shader pre-compile
     let shader_compiler = Compiler::new()?;
-    let mut compile_options = CompileOptions::new()?;
-    compile_options.set_optimization_level(OptimizationLevel::Performance);
+    let included_files = RefCell::new(BTreeSet::new());
+    let profile = Compile_profile::from_env()?;
 
+    //Start from an empty directory so outputs of renamed or removed shaders do not linger
     let result_path = Path::new(&env::var("OUT_DIR")?).join("shaders");
-    if !result_path.exists() {
-        fs::create_dir_all(&result_path).unwrap();
+    if result_path.exists() {
+        fs::remove_dir_all(&result_path)?;
     }
-    for (i, path) in fs::read_dir(Path::new("shaders"))?.map(|entry| entry.unwrap().path()).enumerate() {
+    fs::create_dir_all(&result_path)?;
+
+    //Sorted so that the registry and the report do not depend on the file system
+    let mut paths = fs::read_dir(Path::new(SHADER_DIR))?.map(|entry| Ok(entry?.path())).collect::<Result<Vec<_>, std::io::Error>>()?;
+    paths.sort();
+
+    let mut registry = vec![];
+    let mut outputs = BTreeMap::new();
+    for path in paths {
+        if !path.is_file() {
+            continue
+        }
+
+        let description = get_shader_description(&path)?;
+        let Some((name, stage_info, language)) = get_shader_stage(&path, &description)? else {
+            if !path.extension().and_then(|s| s.to_str()).is_some_and(|extension| NON_SHADER_EXTENSIONS.contains(&extension)) {
+                println!("cargo:warning=Skipped {}: unknown shader stage, expected name.<stage>, name.<stage>.glsl, name.<stage>.hlsl or a stage in its json", path.display());
+            }
+            continue
+        };
+        let (stage_extension, shader_kind, stage, _) = stage_info;
+        let name = name.ok_or_else(|| format!("{}: shader file names have to be valid UTF-8", path.display()))?;
+
         let source = fs::read_to_string(&path)?;
-        let compile_result = shader_compiler.compile_into_spirv(
-            source.as_str(),
-            match path.extension().and_then(|s| s.to_str()).unwrap_or("") {
-                "vert" => ShaderKind::Vertex,
-                "frag" => ShaderKind::Fragment,
-                _ => continue,
-            },
-            path.to_str().unwrap(),
-            "main",
-            Some(&compile_options),
-        )?;
+        for features in get_feature_sets(&path, &description.features)? {
+            let compile = |optimization_level| -> Result<_, Box<dyn Error>> {
+                Ok(shader_compiler.compile_into_spirv(
+                    source.as_str(),
+                    *shader_kind,
+                    path.to_str().unwrap(),
+                    description.entry_point.as_deref().unwrap_or("main"),
+                    Some(&get_compile_options(stage_info, language, &description, &features, &profile, optimization_level, &included_files)?),
+                )?)
+            };
+
+            let compile_result = compile(OPTIMIZATION_LEVELS[profile.optimization_level].1)?;
+            let code = compile_result.as_binary();
+            //The unoptimized module is only built for the size report
+            let unoptimized_size = match OPTIMIZATION_LEVELS[profile.optimization_level].1 {
+                OptimizationLevel::Zero => compile_result.as_binary_u8().len(),
+                _ => compile(OptimizationLevel::Zero)?.as_binary_u8().len(),
+            };
 
-        let mut shader_file = fs::File::create(result_path.join(&format!("{}.spv", path.file_stem().and_then(|s| s.to_str()).unwrap_or(&format!("Unknown {}", i)))))?;
-        shader_file.write_all(compile_result.as_binary_u8())?;
+            //name.<stage>+FEATURE.spv, so shaders of different stages can share a name
+            let file_name = features.iter().fold(format!("{}.{}", name, stage_extension), |file_name, feature| format!("{}+{}", file_name, feature));
+            if let Some(other) = outputs.insert(file_name.clone(), path.clone()) {
+                Err(format!("{} and {} both compile to {}.spv", other.display(), path.display(), file_name))?
+            }
+            validate_shader(code, stage_info, &profile).map_err(|error| format!("{} failed validation: {}", file_name, error))?;
+
+            let shader_path = result_path.join(format!("{}.spv", file_name));
+            let mut shader_file = fs::File::create(&shader_path)?;
+            shader_file.write_all(compile_result.as_binary_u8())?;
+
+            let report = Shader_report{
+                name: name.clone(),
+                stage,
+                features: features.iter().map(|feature| feature.to_string()).collect(),
+                source: path.clone(),
+                spirv: shader_path,
+                entry_points: get_entry_points(code),
+                instruction_count: get_instruction_count(code),
+                unoptimized_size,
+                optimized_size: compile_result.as_binary_u8().len(),
+            };
+
+            registry.push(report);
+        }
     };
 
-    println!("cargo:rerun-if-changed=shaders");
+    //Shader registry, embeds every compiled shader so that adding one needs no code change
+    let mut registry_file = fs::File::create(Path::new(&env::var("OUT_DIR")?).join("shader_registry.rs"))?;
+    writeln!(registry_file, "pub(crate) const EMBEDDED_SHADERS: &[Embedded_shader] = &[")?;
+    for report in &registry {
+        writeln!(registry_file, "    Embedded_shader{{name: {:?}, stage: Shader_stage::{}, features: &{:?}, code: include_bytes!({:?})}},", report.name, report.stage, report.features, report.spirv.to_str().unwrap())?;
+    }
+    writeln!(registry_file, "];")?;
+
+    let manifest = Shader_manifest{
+        optimization_level: OPTIMIZATION_LEVELS[profile.optimization_level].0,
+        debug_info: profile.debug_info,
+        target_env: TARGET_ENVS[profile.target_env].0,
+        spirv_version: profile.spirv_version.map(|version| SPIRV_VERSIONS[version].0),
+        shaders: registry,
+    };
+    fs::write(Path::new(&env::var("OUT_DIR")?).join("rust_vulkan_shaders.json"), serde_json::to_string_pretty(&manifest)?)?;
+
+    println!("cargo:rerun-if-changed={}", SHADER_DIR);
+    for variable in ["RUST_VULKAN_SHADER_OPT", "RUST_VULKAN_SHADER_DEBUG", "RUST_VULKAN_SHADER_TARGET_ENV", "RUST_VULKAN_SHADER_SPIRV_VERSION"] {
+        println!("cargo:rerun-if-env-changed={}", variable);
+    }
+    for path in included_files.borrow().iter() {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
 
     //C Header generate
     let target_path = PathBuf::from(env::var("OUT_DIR")?)  //out
@@ -47,6 +279,11 @@ fn main() -> Result<(), Box<dyn Error>> {
         language: cbindgen::Language::C,
         header: Some("//Made by Han_feng".to_string()),
         include_guard: Some("RUST_VULKAN_LIB_H".to_string()),
+        //Taken from C as plain integers, so nothing in the API refers to them
+        export: cbindgen::ExportConfig{
+            include: vec!["Index_type".to_string(), "Vertex_format".to_string()],
+            ..Default::default()
+        },
         ..Default::default()
     };
 
@@ -57,5 +294,165 @@ fn main() -> Result<(), Box<dyn Error>> {
         .write_to_file(target_path);
 
     println!("cargo:rerun-if-changed=src/lib.rs");
+    //The header also takes the types lib.rs exports from the modules
+    println!("cargo:rerun-if-changed=src/libs");
+    Ok(())
+}
+
+//Both name.vert and name.vert.glsl are accepted, the name is what is left without the stage
+//name.<stage>, name.<stage>.glsl and name.<stage>.hlsl, the stage of the last two may come from the sidecar instead
+fn get_shader_stage(path: &Path, description: &Shader_description) -> Result<Option<Shader_source_info>, Box<dyn Error>>{
+    let find_stage = |extension: &str| SHADER_STAGES.iter().find(|(stage_extension, ..)| *stage_extension == extension);
+    let get_name = |path: &Path| path.file_stem().and_then(|s| s.to_str()).map(|s| s.to_string());
+
+    let declared_stage = match &description.stage {
+        Some(stage) => Some(find_stage(stage).ok_or_else(|| format!("{}: unknown stage {}", path.display(), stage))?),
+        None => None,
+    };
+
+    let (name, stage, language) = match path.extension().and_then(|s| s.to_str()) {
+        Some(extension @ ("glsl" | "hlsl")) => {
+            let language = if extension == "hlsl" { SourceLanguage::HLSL } else { SourceLanguage::GLSL };
+            let stem = Path::new(path.file_stem().unwrap_or_default());
+
+            match stem.extension().and_then(|s| s.to_str()).and_then(find_stage) {
+                Some(stage) => (get_name(stem), Some(stage), language),
+                None => (get_name(path), None, language),
+            }
+        },
+        Some(extension) => (get_name(path), find_stage(extension), SourceLanguage::GLSL),
+        None => return Ok(None),
+    };
+
+    Ok(declared_stage.or(stage).map(|stage| (name, stage, language)))
+}
+
+fn get_shader_description(path: &Path) -> Result<Shader_description, Box<dyn Error>>{
+    let mut description_path = path.as_os_str().to_owned();
+    description_path.push(".json");
+
+    match fs::read_to_string(&description_path) {
+        Ok(source) => serde_json::from_str(&source).map_err(|error| format!("{}: {}", Path::new(&description_path).display(), error).into()),
+        Err(_) => Ok(Shader_description::default()),
+    }
+}
+
+//Every subset of the declared features, in declaration order, starting with the plain shader
+fn get_feature_sets<'a>(path: &Path, features: &'a [String]) -> Result<Vec<Vec<&'a str>>, Box<dyn Error>>{
+    if features.len() > MAX_SHADER_FEATURES {
+        return Err(format!("{}: {} features declared, at most {} are supported", path.display(), features.len(), MAX_SHADER_FEATURES).into())
+    }
+
+    let mut declared = BTreeSet::new();
+    for feature in features {
+        let valid = feature.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') && feature.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid || !declared.insert(feature) {
+            return Err(format!("{}: feature {} is not a unique macro name", path.display(), feature).into())
+        }
+    }
+
+    Ok((0..1usize << features.len()).map(|mask| {
+        features.iter().enumerate().filter(|(index, _)| mask & (1 << index) != 0).map(|(_, feature)| feature.as_str()).collect()
+    }).collect())
+}
+
+fn get_compile_options<'a>(stage_info: &Shader_stage_info, language: SourceLanguage, description: &Shader_description, features: &[&str], profile: &Compile_profile, optimization_level: OptimizationLevel, included_files: &'a RefCell<BTreeSet<PathBuf>>) -> Result<CompileOptions<'a>, Box<dyn Error>>{
+    let mut compile_options = CompileOptions::new()?;
+    compile_options.set_source_language(language);
+    compile_options.set_optimization_level(optimization_level);
+    if profile.debug_info {
+        compile_options.set_generate_debug_info();
+    }
+
+    let (target_env, spirv_version) = profile.get_stage_target(stage_info);
+    compile_options.set_target_env(TargetEnv::Vulkan, TARGET_ENVS[target_env].1 as u32);
+    if let Some(spirv_version) = spirv_version {
+        compile_options.set_target_spirv(SPIRV_VERSIONS[spirv_version].1);
+    }
+
+    for (name, value) in &description.defines {
+        compile_options.add_macro_definition(name, value.as_deref());
+    }
+    for feature in features {
+        compile_options.add_macro_definition(feature, Some("1"));
+    }
+
+    compile_options.set_include_callback(move |requested, include_type, requesting, _| resolve_include(requested, include_type, requesting, included_files));
+
+    Ok(compile_options)
+}
+
+//Quoted includes look next to the including file first, both forms then fall back to shaders/ and shaders/include
+fn resolve_include(requested: &str, include_type: IncludeType, requesting: &str, included_files: &RefCell<BTreeSet<PathBuf>>) -> IncludeCallbackResult{
+    let mut candidates = vec![];
+    if matches!(include_type, IncludeType::Relative) {
+        candidates.push(Path::new(requesting).parent().unwrap_or(Path::new("")).join(requested));
+    }
+    candidates.push(Path::new(SHADER_DIR).join(requested));
+    candidates.push(Path::new(SHADER_INCLUDE_DIR).join(requested));
+
+    let path = candidates.into_iter().find(|candidate| candidate.is_file())
+        .ok_or_else(|| format!("cannot find {} included from {}", requested, requesting))?;
+    let content = fs::read_to_string(&path).map_err(|error| format!("{}: {}", path.display(), error))?;
+
+    included_files.borrow_mut().insert(path.clone());
+
+    Ok(ResolvedInclude{
+        resolved_name: path.to_str().unwrap().to_string(),
+        content,
+    })
+}
+
+//Index of the variable's value among the accepted names, None when it is not set
+fn get_env_option<'a>(variable: &str, names: impl Iterator<Item = &'a str> + Clone) -> Result<Option<usize>, Box<dyn Error>>{
+    match env::var(variable) {
+        Ok(value) => match names.clone().position(|name| name == value) {
+            Some(index) => Ok(Some(index)),
+            None => Err(format!("Unknown {} {}, expected one of {:?}", variable, value, names.collect::<Vec<_>>()))?,
+        },
+        Err(_) => Ok(None),
+    }
+}
+
+fn validate_shader(code: &[u32], stage_info: &Shader_stage_info, profile: &Compile_profile) -> Result<(), Box<dyn Error>>{
+    let (target_env, _) = profile.get_stage_target(stage_info);
+
+    let validator = spirv_tools::val::create(Some(TARGET_ENVS[target_env].2));
+    validator.validate(code, None).map_err(|error| error.to_string())?;
+
     Ok(())
-}
\ No newline at end of file
+}
+
+fn get_instruction_count(code: &[u32]) -> usize{
+    let mut count = 0;
+    let mut offset = 5;
+
+    while let Some(word) = code.get(offset) {
+        count += 1;
+        offset += ((word >> 16) as usize).max(1);
+    }
+
+    count
+}
+
+fn get_entry_points(code: &[u32]) -> Vec<String>{
+    let mut entry_points = vec![];
+    let mut offset = 5;
+
+    if code.first() != Some(&SPIRV_MAGIC) {
+        return entry_points
+    }
+
+    while let Some(word) = code.get(offset) {
+        let word_count = ((word >> 16) as usize).max(1);
+
+        if word & 0xffff == OP_ENTRY_POINT && let Some(name) = code.get(offset+3..offset+word_count) {
+            let bytes = name.iter().flat_map(|word| word.to_le_bytes()).take_while(|&byte| byte != 0).collect::<Vec<_>>();
+            entry_points.push(String::from_utf8_lossy(&bytes).into_owned());
+        }
+
+        offset += word_count;
+    }
+
+    entry_points
+}
@@ -0,0 +1,320 @@
+//Made by Han_feng
+
+#![allow(non_camel_case_types)]
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use rust_vulkan::{Pipeline_cache_header, Pipeline_status, Vulkan_application, Vulkan_config, PIPELINE_CACHE_HEADER_SIZE};
+use sha2::{Digest, Sha256};
+
+//Consts
+const USAGE: &str = "\
+Usage:
+    pipeline_cache build --manifest <manifest.json> --output <cache.bin> [--shader-dir <dir>] [--shader-store <dir>] [--vulkan-path <loader>]
+    pipeline_cache inspect <cache.bin>
+    pipeline_cache check <cache.bin> --vendor <id> --device <id> --uuid <uuid>
+    pipeline_cache diff <cache.bin> <cache.bin>";
+
+//Enums
+enum Command{
+    Build{
+        manifest: PathBuf,
+        output: PathBuf,
+        shader_dir: Option<PathBuf>,
+        shader_store: Option<PathBuf>,
+        vulkan_path: Option<PathBuf>,
+    },
+    Inspect{
+        cache: PathBuf,
+    },
+    Check{
+        cache: PathBuf,
+        vendor_id: u32,
+        device_id: u32,
+        uuid: [u8; 16],
+    },
+    Diff{
+        first: PathBuf,
+        second: PathBuf,
+    },
+}
+
+//Structs
+struct Cache_file{
+    path: PathBuf,
+    data: Vec<u8>,
+}
+
+//Impls
+impl Command{
+    fn parse(args: &[String]) -> Result<Self, String>{
+        let Some((command, args)) = args.split_first() else {
+            return Err(USAGE.to_string())
+        };
+
+        match command.as_str() {
+            "build" => {
+                let mut options = get_options(args, &["--manifest", "--output", "--shader-dir", "--shader-store", "--vulkan-path"])?;
+
+                Ok(Command::Build{
+                    manifest: PathBuf::from(take_option(&mut options, "--manifest")?),
+                    output: PathBuf::from(take_option(&mut options, "--output")?),
+                    shader_dir: options.remove("--shader-dir").map(PathBuf::from),
+                    shader_store: options.remove("--shader-store").map(PathBuf::from),
+                    vulkan_path: options.remove("--vulkan-path").map(PathBuf::from),
+                })
+            },
+            "inspect" => match args {
+                [cache] => Ok(Command::Inspect{cache: PathBuf::from(cache)}),
+                _ => Err(USAGE.to_string()),
+            },
+            "check" => {
+                let Some((cache, args)) = args.split_first() else {
+                    return Err(USAGE.to_string())
+                };
+                let mut options = get_options(args, &["--vendor", "--device", "--uuid"])?;
+
+                Ok(Command::Check{
+                    cache: PathBuf::from(cache),
+                    vendor_id: parse_id(&take_option(&mut options, "--vendor")?)?,
+                    device_id: parse_id(&take_option(&mut options, "--device")?)?,
+                    uuid: parse_uuid(&take_option(&mut options, "--uuid")?)?,
+                })
+            },
+            "diff" => match args {
+                [first, second] => Ok(Command::Diff{first: PathBuf::from(first), second: PathBuf::from(second)}),
+                _ => Err(USAGE.to_string()),
+            },
+            _ => Err(format!("Unknown command {}\n{}", command, USAGE)),
+        }
+    }
+
+    fn run(self) -> Result<(), String>{
+        match self {
+            Command::Build{manifest, output, shader_dir, shader_store, vulkan_path} => {
+                let result = build(&manifest, &output, shader_dir.as_deref(), shader_store, vulkan_path);
+
+                //The device saves its cache on drop as well, never leave a partial one behind
+                if result.is_err() && output.exists() {
+                    let _ = fs::remove_file(&output);
+                }
+
+                result
+            },
+            Command::Inspect{cache} => {
+                let cache = Cache_file::read(cache)?;
+                let header = cache.get_header()?;
+
+                println!("File:           {}", cache.path.display());
+                println!("Size:           {} bytes ({} bytes of payload)", cache.data.len(), cache.data.len() - header.header_size as usize);
+                println!("SHA-256:        {}", cache.get_hash());
+                println!("Header size:    {}", header.header_size);
+                println!("Header version: {}", header.header_version);
+                println!("Vendor id:      {:#06x}", header.vendor_id);
+                println!("Device id:      {:#010x}", header.device_id);
+                println!("UUID:           {}", format_uuid(&header.pipeline_cache_uuid));
+
+                Ok(())
+            },
+            Command::Check{cache, vendor_id, device_id, uuid} => {
+                let cache = Cache_file::read(cache)?;
+
+                match cache.get_header()?.check(vendor_id, device_id, &uuid) {
+                    Ok(()) => {
+                        println!("{} is compatible", cache.path.display());
+                        Ok(())
+                    },
+                    Err(error) => Err(format!("{} is incompatible: {}", cache.path.display(), error)),
+                }
+            },
+            Command::Diff{first, second} => {
+                let first = Cache_file::read(first)?;
+                let second = Cache_file::read(second)?;
+
+                if first.data == second.data {
+                    println!("Caches are identical ({})", first.get_hash());
+                    return Ok(())
+                }
+
+                println!("--- {}", first.path.display());
+                println!("+++ {}", second.path.display());
+
+                match (Pipeline_cache_header::parse(&first.data), Pipeline_cache_header::parse(&second.data)) {
+                    (Ok(first_header), Ok(second_header)) => {
+                        print_difference("Header size", first_header.header_size, second_header.header_size);
+                        print_difference("Header version", first_header.header_version, second_header.header_version);
+                        print_difference("Vendor id", format!("{:#06x}", first_header.vendor_id), format!("{:#06x}", second_header.vendor_id));
+                        print_difference("Device id", format!("{:#010x}", first_header.device_id), format!("{:#010x}", second_header.device_id));
+                        print_difference("UUID", format_uuid(&first_header.pipeline_cache_uuid), format_uuid(&second_header.pipeline_cache_uuid));
+                    },
+                    (first_header, second_header) => {
+                        print_difference("Header", format!("{:?}", first_header.err()), format!("{:?}", second_header.err()));
+                    }
+                }
+
+                print_difference("Size", first.data.len(), second.data.len());
+                print_difference("SHA-256", first.get_hash(), second.get_hash());
+
+                //The payload layout is driver defined, so the first diverging byte is all we can point at
+                let offset = first.data.iter().zip(&second.data).position(|(a, b)| a != b)
+                    .unwrap_or(first.data.len().min(second.data.len()));
+                if offset >= PIPELINE_CACHE_HEADER_SIZE {
+                    println!("Payload differs from offset {:#x}", offset);
+                }
+
+                Ok(())
+            },
+        }
+    }
+}
+
+impl Cache_file{
+    fn read(path: PathBuf) -> Result<Self, String>{
+        let data = fs::read(&path).map_err(|error| format!("Failed to read {}: {}", path.display(), error))?;
+
+        Ok(Cache_file{
+            path, data
+        })
+    }
+
+    fn get_header(&self) -> Result<Pipeline_cache_header, String>{
+        Pipeline_cache_header::parse(&self.data).map_err(|error| format!("{} is not a valid pipeline cache: {}", self.path.display(), error))
+    }
+
+    fn get_hash(&self) -> String{
+        format!("{:x}", Sha256::digest(&self.data))
+    }
+}
+
+//Functions
+fn main() -> ExitCode{
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    match Command::parse(&args).and_then(Command::run) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn build(manifest: &Path, output: &Path, shader_dir: Option<&Path>, shader_store: Option<PathBuf>, vulkan_path: Option<PathBuf>) -> Result<(), String>{
+    //Start from an empty cache so that the blob only holds what the manifest describes
+    if output.exists() {
+        fs::remove_file(output).map_err(|error| format!("Failed to remove {}: {}", output.display(), error))?;
+    }
+
+    let config = Vulkan_config{
+        vulkan_path,
+        pipeline_cache_path: Some(output.to_path_buf()),
+        pipeline_manifest_path: None,
+        shader_store_path: shader_store,
+    };
+
+    //No display handle, so neither a window nor a surface is ever created
+    let mut application = Vulkan_application::new_headless(None, config)
+        .map_err(|code| format!("Failed to create a headless vulkan device ({:?})", code))?;
+
+    //Compiled modules on disk shadow the embedded ones, named after their file name up to the stage, e.g. sprite.frag.spv registers sprite
+    if let Some(shader_dir) = shader_dir {
+        let entries = fs::read_dir(shader_dir).map_err(|error| format!("Failed to read {}: {}", shader_dir.display(), error))?;
+        let mut paths = entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect::<Vec<_>>();
+        paths.sort();
+
+        for path in paths {
+            let Some(name) = path.file_name().and_then(|name| name.to_str()).and_then(|name| name.split('.').next()).filter(|_| path.extension().is_some_and(|extension| extension == "spv")) else {
+                continue
+            };
+
+            application.register_shader_file(name, &path)
+                .map_err(|code| format!("Failed to register shader {} ({:?})", path.display(), code))?;
+        }
+    }
+
+    application.load_pipeline_manifest(manifest)
+        .map_err(|code| format!("Failed to load pipeline manifest {} ({:?})", manifest.display(), code))?;
+
+    let reports = application.wait_pipelines();
+    for report in &reports {
+        println!("{:<32} {:?}", report.name, report.status);
+    }
+
+    let failed = reports.iter().filter(|report| report.status == Pipeline_status::Failed).count();
+    if failed > 0 {
+        return Err(format!("{} of {} pipelines failed to compile", failed, reports.len()))
+    }
+
+    application.save_pipeline_cache()
+        .map_err(|code| format!("Failed to save pipeline cache to {} ({:?})", output.display(), code))?;
+
+    let data = fs::read(output).map_err(|error| format!("Failed to read {}: {}", output.display(), error))?;
+    let header = Pipeline_cache_header::parse(&data).map_err(|error| format!("Written cache is invalid: {}", error))?;
+
+    println!("Wrote {} bytes for vendor {:#06x} device {:#010x} to {}", data.len(), header.vendor_id, header.device_id, output.display());
+
+    Ok(())
+}
+
+fn get_options(args: &[String], names: &[&str]) -> Result<HashMap<String, String>, String>{
+    let mut options = HashMap::new();
+    let mut args = args.iter();
+
+    while let Some(name) = args.next() {
+        if !names.contains(&name.as_str()) {
+            return Err(format!("Unknown option {}\n{}", name, USAGE))
+        }
+
+        let value = args.next().ok_or_else(|| format!("Missing value for {}", name))?;
+        if options.insert(name.clone(), value.clone()).is_some() {
+            return Err(format!("Option {} is given more than once", name))
+        }
+    }
+
+    Ok(options)
+}
+
+fn take_option(options: &mut HashMap<String, String>, name: &str) -> Result<String, String>{
+    options.remove(name).ok_or_else(|| format!("Missing option {}\n{}", name, USAGE))
+}
+
+//Accepts both decimal and 0x prefixed hexadecimal ids, as printed by vulkaninfo
+fn parse_id(value: &str) -> Result<u32, String>{
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    }.map_err(|_| format!("Invalid id {}", value))
+}
+
+fn parse_uuid(value: &str) -> Result<[u8; 16], String>{
+    let digits = value.chars().filter(|c| *c != '-').collect::<String>();
+    if digits.len() != 32 || !digits.is_ascii() {
+        return Err(format!("Invalid uuid {}", value))
+    }
+
+    let mut uuid = [0; 16];
+    for (index, byte) in uuid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[index*2..index*2+2], 16).map_err(|_| format!("Invalid uuid {}", value))?;
+    }
+
+    Ok(uuid)
+}
+
+fn format_uuid(uuid: &[u8; 16]) -> String{
+    uuid.iter().enumerate().map(|(index, byte)| match index {
+        4 | 6 | 8 | 10 => format!("-{:02x}", byte),
+        _ => format!("{:02x}", byte),
+    }).collect()
+}
+
+fn print_difference<T: PartialEq + std::fmt::Display>(field: &str, first: T, second: T){
+    if first == second {
+        println!("  {:<16}{}", field, first);
+    }
+    else {
+        println!("- {:<16}{}", field, first);
+        println!("+ {:<16}{}", field, second);
+    }
+}
@@ -5,11 +5,25 @@
 
 mod libs;
 
-use std::ffi::{c_char, c_uint, c_void};
+use std::ffi::{c_char, c_uint, c_ulonglong, c_void};
+use std::path::PathBuf;
 use libs::utils::C_char_extension;
 
 pub use libs::utils::Status_code;
-pub use libs::vulkan_application::{Vulkan_application, Window};
+pub use libs::vulkan_application::{Vulkan_application, Vulkan_config, Vulkan_prewarm, Window};
+pub use libs::pipelines::{Pipeline_handle, Pipeline_report, Pipeline_status, DEFAULT_PIPELINE_NAME};
+pub use libs::pipeline_manifest::{Blend_mode, Cull_mode, Manifest_error, Pipeline_entry, Pipeline_manifest, Render_format, Topology};
+pub use libs::shader_reflection::{Descriptor_binding, Interface_variable, Pipeline_reflection, Push_constant_range, Reflection_error, Shader_reflection};
+pub use libs::shader_store::{Shader_hash, Shader_store, Shader_store_error, SHADER_HASH_PREFIX, SHADER_HASH_SIZE};
+pub use libs::shaders::{find_embedded_shader, find_embedded_shader_variant, get_embedded_shaders, get_shader_report, load_spirv, Embedded_shader, Shader_error, Shader_stage, SPIRV_MAGIC};
+pub use libs::vertex_buffers::{check_vertex_input, Colored_vertex, Raw_vertex_attribute, Vertex, Vertex_attribute, Vertex_buffer_handle, Vertex_format, Vertex_layout, Vertex_layout_error};
+pub use libs::index_buffers::{Index, Index_buffer_handle, Index_type};
+pub use libs::draw_list::{get_draw_range, Draw_command, Draw_error, MAX_DRAW_DESCRIPTOR_SETS};
+pub use libs::uniform_buffers::Uniform_buffer_handle;
+pub use libs::descriptors::Descriptor_set_handle;
+pub use libs::textures::{decode_image, decode_image_file, Image_data, Texture_error, Texture_handle};
+pub use libs::memory_allocator::{Free_list, Memory_report, Memory_stats, Pool_kind, Pool_stats};
+pub use libs::pipeline_cache::{Pipeline_cache_header, Pipeline_cache_error, PIPELINE_CACHE_HEADER_SIZE, validate_pipeline_cache};
 
 #[cfg(debug_assertions)]
 pub use libs::utils::{Logger, Log_level};
@@ -21,7 +35,6 @@ cfg_if::cfg_if!{
         use std::fs::{File, create_dir_all};
         use std::io::Write;
         use log::{Level, LevelFilter};
-        use std::ffi::c_ulonglong;
     }
 }
 
@@ -110,12 +123,17 @@ pub extern "C" fn destroy_logger(logger: *mut Logger){
 //Vulkan
 #[unsafe(no_mangle)]
 pub extern "C" fn get_vulkan_application(window_handle: *mut c_void, width: c_uint, height: c_uint, vulkan_path: *const c_char, vulkan_application: *mut *mut Vulkan_application) -> Status_code{
+    get_vulkan_application_with_cache(window_handle, width, height, vulkan_path, std::ptr::null(), vulkan_application)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn get_vulkan_application_with_cache(window_handle: *mut c_void, width: c_uint, height: c_uint, vulkan_path: *const c_char, pipeline_cache_path: *const c_char, vulkan_application: *mut *mut Vulkan_application) -> Status_code{
     let window = match Window::new(window_handle, width as u32, height as u32){
         Ok(window) => window,
         Err(code) => return code,
     };
 
-    let application = Box::new(match Vulkan_application::new(window, if vulkan_path.is_null() {None} else {Some(vulkan_path.to_str())}) {
+    let application = Box::new(match Vulkan_application::new(window, get_config(vulkan_path, pipeline_cache_path, std::ptr::null())) {
         Ok(application) => application,
         Err(code) => return code,
     });
@@ -125,10 +143,448 @@ pub extern "C" fn get_vulkan_application(window_handle: *mut c_void, width: c_ui
     Status_code::Success
 }
 
+#[unsafe(no_mangle)]
+pub extern "C" fn prewarm_vulkan_application(vulkan_path: *const c_char, pipeline_cache_path: *const c_char, pipeline_manifest_path: *const c_char, vulkan_prewarm: *mut *mut Vulkan_prewarm) -> Status_code{
+    let prewarm = Box::new(match Vulkan_application::prewarm(Some(Window::get_display_handle()), get_config(vulkan_path, pipeline_cache_path, pipeline_manifest_path)) {
+        Ok(prewarm) => prewarm,
+        Err(code) => return code,
+    });
+
+    unsafe { *vulkan_prewarm = Box::into_raw(prewarm) }
+
+    Status_code::Success
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn is_prewarm_finished(vulkan_prewarm: *const Vulkan_prewarm, finished: *mut bool) -> Status_code{
+    unsafe {
+        *finished = (*vulkan_prewarm).is_finished();
+    }
+
+    Status_code::Success
+}
+
+//Blocks until the prewarm thread is done, the prewarm handle is consumed either way
+#[unsafe(no_mangle)]
+pub extern "C" fn finish_prewarm(vulkan_prewarm: *mut Vulkan_prewarm, vulkan_application: *mut *mut Vulkan_application) -> Status_code{
+    let prewarm = unsafe { Box::from_raw(vulkan_prewarm) };
+
+    let application = Box::new(match prewarm.wait() {
+        Ok(application) => application,
+        Err(code) => return code,
+    });
+
+    unsafe { *vulkan_application = Box::into_raw(application) }
+
+    Status_code::Success
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn attach_surface(vulkan_application: *mut Vulkan_application, window_handle: *mut c_void, width: c_uint, height: c_uint) -> Status_code{
+    let window = match Window::new(window_handle, width as u32, height as u32){
+        Ok(window) => window,
+        Err(code) => return code,
+    };
+
+    unsafe {
+        match (*vulkan_application).attach_surface(window){
+            Ok(_) => Status_code::Success,
+            Err(code) => code,
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn save_pipeline_cache(vulkan_application: *const Vulkan_application) -> Status_code {
+    unsafe {
+        match (*vulkan_application).save_pipeline_cache(){
+            Ok(_) => Status_code::Success,
+            Err(code) => code,
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn load_pipeline_manifest(vulkan_application: *mut Vulkan_application, pipeline_manifest_path: *const c_char) -> Status_code {
+    unsafe {
+        match (*vulkan_application).load_pipeline_manifest(pipeline_manifest_path.to_str()){
+            Ok(_) => Status_code::Success,
+            Err(code) => code,
+        }
+    }
+}
+
+//Embedded shaders live as long as the library, so the returned pointer never dangles
+#[unsafe(no_mangle)]
+pub extern "C" fn get_embedded_shader(name: *const c_char, data: *mut *const u8, size: *mut c_ulonglong, stage: *mut Shader_stage) -> Status_code {
+    let Some(shader) = find_embedded_shader(name.to_str()) else {
+        return Status_code::Failure
+    };
+
+    unsafe {
+        *data = shader.code.as_ptr();
+        *size = shader.code.len() as c_ulonglong;
+        *stage = shader.stage;
+    }
+
+    Status_code::Success
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn get_embedded_shader_variant(name: *const c_char, features: *const *const c_char, feature_count: c_ulonglong, data: *mut *const u8, size: *mut c_ulonglong, stage: *mut Shader_stage) -> Status_code {
+    let features = if features.is_null() {
+        vec![]
+    }
+    else {
+        unsafe { std::slice::from_raw_parts(features, feature_count as usize) }.iter().map(|feature| feature.to_str()).collect::<Vec<_>>()
+    };
+
+    let Some(shader) = find_embedded_shader_variant(name.to_str(), &features) else {
+        return Status_code::Failure
+    };
+
+    unsafe {
+        *data = shader.code.as_ptr();
+        *size = shader.code.len() as c_ulonglong;
+        *stage = shader.stage;
+    }
+
+    Status_code::Success
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn register_shader(vulkan_application: *mut Vulkan_application, name: *const c_char, data: *const u8, size: c_ulonglong) -> Status_code {
+    if data.is_null() {
+        return Status_code::Failure
+    }
+
+    unsafe {
+        match (*vulkan_application).register_shader(name.to_str(), std::slice::from_raw_parts(data, size as usize)){
+            Ok(_) => Status_code::Success,
+            Err(code) => code,
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn register_shader_file(vulkan_application: *mut Vulkan_application, name: *const c_char, path: *const c_char) -> Status_code {
+    unsafe {
+        match (*vulkan_application).register_shader_file(name.to_str(), path.to_str()){
+            Ok(_) => Status_code::Success,
+            Err(code) => code,
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn open_shader_store(vulkan_application: *mut Vulkan_application, path: *const c_char) -> Status_code {
+    unsafe {
+        match (*vulkan_application).open_shader_store(path.to_str()){
+            Ok(_) => Status_code::Success,
+            Err(code) => code,
+        }
+    }
+}
+
+//Writes the SHADER_HASH_SIZE bytes of the module's hash, manifests refer to it as sha256:<hex>
+#[unsafe(no_mangle)]
+pub extern "C" fn store_shader(vulkan_application: *mut Vulkan_application, data: *const u8, size: c_ulonglong, hash: *mut u8) -> Status_code {
+    if data.is_null() {
+        return Status_code::Failure
+    }
+
+    unsafe {
+        match (*vulkan_application).store_shader(std::slice::from_raw_parts(data, size as usize)){
+            Ok(stored_hash) => {
+                std::ptr::copy_nonoverlapping(stored_hash.0.as_ptr(), hash, SHADER_HASH_SIZE);
+                Status_code::Success
+            },
+            Err(code) => code,
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn get_pipeline_handle(vulkan_application: *const Vulkan_application, name: *const c_char, pipeline: *mut c_ulonglong) -> Status_code {
+    unsafe {
+        (*vulkan_application).get_pipeline_handle(name.to_str()).map(|handle| {
+            *pipeline = handle.0;
+            Status_code::Success
+        }).unwrap_or(Status_code::Failure)
+    }
+}
+
+//Blocks until every pipeline finished compiling and reports how many of them failed
+#[unsafe(no_mangle)]
+pub extern "C" fn wait_pipelines(vulkan_application: *const Vulkan_application, failed_count: *mut c_ulonglong) -> Status_code {
+    unsafe {
+        let reports = (*vulkan_application).wait_pipelines();
+        *failed_count = reports.iter().filter(|report| report.status == Pipeline_status::Failed).count() as c_ulonglong;
+    }
+
+    Status_code::Success
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn get_pipeline_status(vulkan_application: *const Vulkan_application, pipeline: c_ulonglong, status: *mut Pipeline_status) -> Status_code {
+    unsafe {
+        (*vulkan_application).get_pipeline_status(Pipeline_handle(pipeline)).map(|s| {
+            *status = s;
+            Status_code::Success
+        }).unwrap_or(Status_code::Failure)
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn wait_pipeline(vulkan_application: *const Vulkan_application, pipeline: c_ulonglong, status: *mut Pipeline_status) -> Status_code {
+    unsafe {
+        (*vulkan_application).wait_pipeline(Pipeline_handle(pipeline)).map(|s| {
+            *status = s;
+            Status_code::Success
+        }).unwrap_or(Status_code::Failure)
+    }
+}
+
+//Totals over every memory pool
+#[unsafe(no_mangle)]
+pub extern "C" fn get_memory_stats(vulkan_application: *const Vulkan_application, stats: *mut Memory_stats) -> Status_code {
+    unsafe {
+        *stats = (*vulkan_application).get_memory_report().total;
+    }
+
+    Status_code::Success
+}
+
+//Attributes describe one vertex of stride bytes, size has to be a whole number of vertices
+//Attribute formats are Vertex_format values
+#[unsafe(no_mangle)]
+pub extern "C" fn create_vertex_buffer(vulkan_application: *mut Vulkan_application, data: *const u8, size: c_ulonglong, stride: c_uint, attributes: *const Raw_vertex_attribute, attribute_count: c_ulonglong, vertex_buffer: *mut c_ulonglong) -> Status_code {
+    if data.is_null() || attributes.is_null() {
+        return Status_code::Failure
+    }
+
+    let attributes = match unsafe { std::slice::from_raw_parts(attributes, attribute_count as usize) }.iter().map(|&attribute| Vertex_attribute::try_from(attribute)).collect() {
+        Ok(attributes) => attributes,
+        Err(code) => return code,
+    };
+    let layout = Vertex_layout{
+        stride: stride as u32,
+        attributes,
+    };
+
+    unsafe {
+        match (*vulkan_application).create_vertex_buffer_from_bytes(layout, std::slice::from_raw_parts(data, size as usize)){
+            Ok(handle) => {
+                *vertex_buffer = handle.0;
+                Status_code::Success
+            },
+            Err(code) => code,
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn destroy_vertex_buffer(vulkan_application: *mut Vulkan_application, vertex_buffer: c_ulonglong) -> Status_code {
+    unsafe {
+        match (*vulkan_application).destroy_vertex_buffer(Vertex_buffer_handle(vertex_buffer)){
+            Ok(_) => Status_code::Success,
+            Err(code) => code,
+        }
+    }
+}
+
+//Index type is an Index_type value
+#[unsafe(no_mangle)]
+pub extern "C" fn create_index_buffer(vulkan_application: *mut Vulkan_application, data: *const u8, size: c_ulonglong, index_type: c_uint, index_buffer: *mut c_ulonglong) -> Status_code {
+    if data.is_null() {
+        return Status_code::Failure
+    }
+    let index_type = match Index_type::try_from(index_type as u32) {
+        Ok(index_type) => index_type,
+        Err(code) => return code,
+    };
+
+    unsafe {
+        match (*vulkan_application).create_index_buffer_from_bytes(index_type, std::slice::from_raw_parts(data, size as usize)){
+            Ok(handle) => {
+                *index_buffer = handle.0;
+                Status_code::Success
+            },
+            Err(code) => code,
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn destroy_index_buffer(vulkan_application: *mut Vulkan_application, index_buffer: c_ulonglong) -> Status_code {
+    unsafe {
+        match (*vulkan_application).destroy_index_buffer(Index_buffer_handle(index_buffer)){
+            Ok(_) => Status_code::Success,
+            Err(code) => code,
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn create_uniform_buffer(vulkan_application: *mut Vulkan_application, size: c_ulonglong, uniform_buffer: *mut c_ulonglong) -> Status_code {
+    unsafe {
+        match (*vulkan_application).create_uniform_buffer(size as u64){
+            Ok(handle) => {
+                *uniform_buffer = handle.0;
+                Status_code::Success
+            },
+            Err(code) => code,
+        }
+    }
+}
+
+//Size has to match the buffer, the next draw_frame picks the data up
+#[unsafe(no_mangle)]
+pub extern "C" fn update_uniform_buffer(vulkan_application: *mut Vulkan_application, uniform_buffer: c_ulonglong, data: *const u8, size: c_ulonglong) -> Status_code {
+    if data.is_null() {
+        return Status_code::Failure
+    }
+
+    unsafe {
+        match (*vulkan_application).update_uniform_buffer(Uniform_buffer_handle(uniform_buffer), std::slice::from_raw_parts(data, size as usize)){
+            Ok(_) => Status_code::Success,
+            Err(code) => code,
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn destroy_uniform_buffer(vulkan_application: *mut Vulkan_application, uniform_buffer: c_ulonglong) -> Status_code {
+    unsafe {
+        match (*vulkan_application).destroy_uniform_buffer(Uniform_buffer_handle(uniform_buffer)){
+            Ok(_) => Status_code::Success,
+            Err(code) => code,
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn create_descriptor_set(vulkan_application: *mut Vulkan_application, pipeline: c_ulonglong, set: c_uint, descriptor_set: *mut c_ulonglong) -> Status_code {
+    unsafe {
+        match (*vulkan_application).create_descriptor_set(Pipeline_handle(pipeline), set as u32){
+            Ok(handle) => {
+                *descriptor_set = handle.0;
+                Status_code::Success
+            },
+            Err(code) => code,
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn bind_uniform_buffer(vulkan_application: *mut Vulkan_application, descriptor_set: c_ulonglong, binding: c_uint, uniform_buffer: c_ulonglong) -> Status_code {
+    unsafe {
+        match (*vulkan_application).bind_uniform_buffer(Descriptor_set_handle(descriptor_set), binding as u32, Uniform_buffer_handle(uniform_buffer)){
+            Ok(_) => Status_code::Success,
+            Err(code) => code,
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn destroy_descriptor_set(vulkan_application: *mut Vulkan_application, descriptor_set: c_ulonglong) -> Status_code {
+    unsafe {
+        match (*vulkan_application).destroy_descriptor_set(Descriptor_set_handle(descriptor_set)){
+            Ok(_) => Status_code::Success,
+            Err(code) => code,
+        }
+    }
+}
+
+//JPEG or PNG file
+#[unsafe(no_mangle)]
+pub extern "C" fn load_texture_file(vulkan_application: *mut Vulkan_application, path: *const c_char, texture: *mut c_ulonglong) -> Status_code {
+    unsafe {
+        match (*vulkan_application).load_texture_file(path.to_str()){
+            Ok(handle) => {
+                *texture = handle.0;
+                Status_code::Success
+            },
+            Err(code) => code,
+        }
+    }
+}
+
+//JPEG or PNG data, only read during the call
+#[unsafe(no_mangle)]
+pub extern "C" fn load_texture(vulkan_application: *mut Vulkan_application, data: *const u8, size: c_ulonglong, texture: *mut c_ulonglong) -> Status_code {
+    if data.is_null() {
+        return Status_code::Failure
+    }
+
+    unsafe {
+        match (*vulkan_application).load_texture(std::slice::from_raw_parts(data, size as usize)){
+            Ok(handle) => {
+                *texture = handle.0;
+                Status_code::Success
+            },
+            Err(code) => code,
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn get_texture_size(vulkan_application: *const Vulkan_application, texture: c_ulonglong, width: *mut c_uint, height: *mut c_uint) -> Status_code {
+    unsafe {
+        (*vulkan_application).get_texture_size(Texture_handle(texture)).map(|(w, h)| {
+            *width = w as c_uint;
+            *height = h as c_uint;
+            Status_code::Success
+        }).unwrap_or(Status_code::Failure)
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn bind_texture(vulkan_application: *mut Vulkan_application, descriptor_set: c_ulonglong, binding: c_uint, texture: c_ulonglong) -> Status_code {
+    unsafe {
+        match (*vulkan_application).bind_texture(Descriptor_set_handle(descriptor_set), binding as u32, Texture_handle(texture)){
+            Ok(_) => Status_code::Success,
+            Err(code) => code,
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn destroy_texture(vulkan_application: *mut Vulkan_application, texture: c_ulonglong) -> Status_code {
+    unsafe {
+        match (*vulkan_application).destroy_texture(Texture_handle(texture)){
+            Ok(_) => Status_code::Success,
+            Err(code) => code,
+        }
+    }
+}
+
+//Draws the default triangle
 #[unsafe(no_mangle)]
 pub extern "C" fn draw_frame(vulkan_application: *mut Vulkan_application) -> Status_code {
+    let draw_command = Draw_command::new(Pipeline_handle::DEFAULT, Vertex_buffer_handle::DEFAULT);
+
+    draw_frame_with_commands(vulkan_application, &draw_command, 1, std::ptr::null(), 0)
+}
+
+//The draw commands and push constants are only read during the call, a frame without any draws just clears the screen
+#[unsafe(no_mangle)]
+pub extern "C" fn draw_frame_with_commands(vulkan_application: *mut Vulkan_application, draw_commands: *const Draw_command, draw_count: c_ulonglong, push_constants: *const u8, push_constant_size: c_ulonglong) -> Status_code {
+    let draw_commands = if draw_commands.is_null() {
+        &[][..]
+    }
+    else {
+        unsafe { std::slice::from_raw_parts(draw_commands, draw_count as usize) }
+    };
+    let push_constants = if push_constants.is_null() {
+        &[][..]
+    }
+    else {
+        unsafe { std::slice::from_raw_parts(push_constants, push_constant_size as usize) }
+    };
+
     unsafe {
-        match (*vulkan_application).draw_frame(){
+        match (*vulkan_application).draw_frame(draw_commands, push_constants){
             Ok(_) => Status_code::Success,
             Err(code) => code,
         }
@@ -140,4 +596,15 @@ pub extern "C" fn destroy_vulkan_application(vulkan_application: *mut Vulkan_app
     unsafe {
         let _ = Box::from_raw(vulkan_application);
     }
+}
+
+fn get_config(vulkan_path: *const c_char, pipeline_cache_path: *const c_char, pipeline_manifest_path: *const c_char) -> Vulkan_config{
+    let to_path = |path: *const c_char| if path.is_null() {None} else {Some(PathBuf::from(path.to_str()))};
+
+    Vulkan_config{
+        vulkan_path: to_path(vulkan_path),
+        pipeline_cache_path: to_path(pipeline_cache_path),
+        pipeline_manifest_path: to_path(pipeline_manifest_path),
+        shader_store_path: None,
+    }
 }
\ No newline at end of file
@@ -0,0 +1,119 @@
+//Made by Han_feng
+
+use ash::vk;
+use crate::libs::memory_allocator::{Allocation, Memory_allocator, Memory_usage, Pool_kind};
+use crate::libs::utils::Vulkan_result;
+
+//Structs
+pub(crate) struct Buffer{
+    pub(crate) buffer: vk::Buffer,
+    allocation: Allocation,
+}
+
+//Functions
+//Records into a throwaway command buffer and blocks until the queue has executed it
+pub(crate) fn submit_once(device: &ash::Device, pool: vk::CommandPool, queue: vk::Queue, record: impl FnOnce(vk::CommandBuffer)) -> Vulkan_result<()>{
+    let alloc_info = vk::CommandBufferAllocateInfo::default()
+        .command_pool(pool)
+        .level(vk::CommandBufferLevel::PRIMARY)
+        .command_buffer_count(1);
+
+    let buffer = unsafe { device.allocate_command_buffers(&alloc_info)? }[0];
+
+    let result = (|| -> Vulkan_result<()> {
+        let begin_info = vk::CommandBufferBeginInfo::default()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+        unsafe {
+            device.begin_command_buffer(buffer, &begin_info)?;
+            record(buffer);
+            device.end_command_buffer(buffer)?;
+
+            let fence = device.create_fence(&vk::FenceCreateInfo::default(), None)?;
+            let command_buffers = [buffer];
+            let submit_infos = [
+                vk::SubmitInfo::default()
+                    .command_buffers(&command_buffers)
+            ];
+
+            let result = device.queue_submit(queue, &submit_infos, fence)
+                .and_then(|_| device.wait_for_fences(&[fence], true, u64::MAX));
+            device.destroy_fence(fence, None);
+
+            Ok(result?)
+        }
+    })();
+
+    unsafe { device.free_command_buffers(pool, &[buffer]) };
+
+    result
+}
+
+//Impls
+impl Buffer{
+    pub(crate) fn new(device: &ash::Device, allocator: &mut Memory_allocator, size: vk::DeviceSize, usage: vk::BufferUsageFlags, memory_usage: Memory_usage) -> Vulkan_result<Self>{
+        let create_info = vk::BufferCreateInfo::default()
+            .size(size)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+
+        let buffer = unsafe { device.create_buffer(&create_info, None)? };
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+
+        let allocation = match allocator.allocate(device, requirements, memory_usage, Pool_kind::Linear) {
+            Ok(allocation) => allocation,
+            Err(code) => {
+                unsafe { device.destroy_buffer(buffer, None) };
+                return Err(code)
+            }
+        };
+
+        let result = Buffer{
+            buffer, allocation
+        };
+
+        if let Err(error) = unsafe { device.bind_buffer_memory(buffer, result.allocation.memory, result.allocation.offset) } {
+            result.destroy(device, allocator);
+            Err(error)?
+        }
+
+        Ok(result)
+    }
+
+    //Copied through a staging buffer, which is gone again once this returns
+    pub(crate) fn new_device_local(device: &ash::Device, allocator: &mut Memory_allocator, pool: vk::CommandPool, queue: vk::Queue, data: &[u8], usage: vk::BufferUsageFlags) -> Vulkan_result<Self>{
+        let size = data.len() as vk::DeviceSize;
+
+        let staging = Self::new(device, allocator, size, vk::BufferUsageFlags::TRANSFER_SRC, Memory_usage::Staging)?;
+        let result = staging.write(0, data).and_then(|_| {
+            let buffer = Self::new(device, allocator, size, usage | vk::BufferUsageFlags::TRANSFER_DST, Memory_usage::Gpu_only)?;
+
+            let regions = [
+                vk::BufferCopy::default()
+                    .size(size)
+            ];
+
+            match submit_once(device, pool, queue, |command_buffer| unsafe { device.cmd_copy_buffer(command_buffer, staging.buffer, buffer.buffer, &regions) }) {
+                Ok(_) => Ok(buffer),
+                Err(code) => {
+                    buffer.destroy(device, allocator);
+                    Err(code)
+                }
+            }
+        });
+
+        staging.destroy(device, allocator);
+
+        result
+    }
+
+    //Only valid for buffers in host visible memory
+    pub(crate) fn write(&self, offset: vk::DeviceSize, data: &[u8]) -> Vulkan_result<()>{
+        self.allocation.write(offset, data)
+    }
+
+    pub(crate) fn destroy(self, device: &ash::Device, allocator: &mut Memory_allocator){
+        unsafe { device.destroy_buffer(self.buffer, None) };
+        allocator.free(device, self.allocation);
+    }
+}
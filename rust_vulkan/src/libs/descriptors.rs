@@ -0,0 +1,337 @@
+//Made by Han_feng
+
+use ash::vk;
+use crate::libs::shader_reflection::Descriptor_binding;
+use crate::libs::uniform_buffers::Uniform_buffer;
+use crate::libs::utils::{Status_code, Vulkan_result};
+use crate::save_log;
+
+cfg_if::cfg_if! {
+    if #[cfg(debug_assertions)] {
+        use crate::libs::utils::Log_level;
+        use log::Level;
+    }
+}
+
+//Consts
+const POOL_SET_COUNT: u32 = 64;
+const POOL_TYPE_COUNT: usize = 6;
+//Every pool reserves this many descriptors of each type per set it can hold
+const POOL_DESCRIPTOR_TYPES: [(vk::DescriptorType, u32); POOL_TYPE_COUNT] = [
+    (vk::DescriptorType::UNIFORM_BUFFER, 2),
+    (vk::DescriptorType::COMBINED_IMAGE_SAMPLER, 2),
+    (vk::DescriptorType::STORAGE_BUFFER, 1),
+    (vk::DescriptorType::SAMPLED_IMAGE, 1),
+    (vk::DescriptorType::SAMPLER, 1),
+    (vk::DescriptorType::STORAGE_IMAGE, 1),
+];
+
+//Structs
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Descriptor_set_handle(pub u64);
+
+//Hands out sets from a list of pools and opens a new pool before the current ones run out
+//Vulkan 1.0 does not report an exhausted pool, so the remaining capacity of each is tracked here
+#[derive(Default)]
+struct Descriptor_allocator{
+    pools: Vec<Descriptor_pool>,
+}
+
+struct Descriptor_pool{
+    pool: vk::DescriptorPool,
+    free_sets: u32,
+    //Per entry of POOL_DESCRIPTOR_TYPES
+    free_descriptors: [u32; POOL_TYPE_COUNT],
+}
+
+//A descriptor set of one set index, allocated once per frame in flight so frames never share it
+//The bindings carry the set index, so a set only matches pipelines with identical bindings at that index
+pub(crate) struct Descriptor_set{
+    pub(crate) bindings: Vec<Descriptor_binding>,
+    //Owned by the pipeline the set was created from, which a shader reload replaces
+    #[cfg(all(debug_assertions, feature = "hot_reload"))]
+    layout: vk::DescriptorSetLayout,
+    //Set and the pool it came from, per frame
+    sets: Vec<(vk::DescriptorSet, usize)>,
+    //Descriptors each set takes from its pool
+    pool_needs: [u32; POOL_TYPE_COUNT],
+    //Per binding, a set is only drawn with once every binding has been written
+    written: Vec<bool>,
+}
+
+//Destroyed sets leave an empty slot behind so handles stay stable
+#[derive(Default)]
+pub(crate) struct Descriptor_sets{
+    allocator: Descriptor_allocator,
+    sets: Vec<Option<Descriptor_set>>,
+}
+
+//Functions
+//Descriptors of each pool type a set with these bindings takes, fails when no pool could ever hold them
+#[allow(unused_variables)]
+fn get_pool_needs(bindings: &[Descriptor_binding]) -> Vulkan_result<[u32; POOL_TYPE_COUNT]>{
+    let mut needs = [0; POOL_TYPE_COUNT];
+
+    for binding in bindings {
+        let Some(index) = POOL_DESCRIPTOR_TYPES.iter().position(|&(descriptor_type, _)| descriptor_type == binding.descriptor_type) else {
+            save_log!(Log_level::General, Level::Error, "Binding {} is a {:?}, descriptor pools do not hold that type", binding.binding, binding.descriptor_type);
+            return Err(Status_code::Failure)
+        };
+        needs[index] += binding.count;
+    }
+
+    if let Some(index) = needs.iter().zip(POOL_DESCRIPTOR_TYPES).position(|(&need, (_, count))| need > count * POOL_SET_COUNT) {
+        save_log!(Log_level::General, Level::Error, "Set needs {} descriptors of type {:?}, a descriptor pool holds {}", needs[index], POOL_DESCRIPTOR_TYPES[index].0, POOL_DESCRIPTOR_TYPES[index].1 * POOL_SET_COUNT);
+        return Err(Status_code::Failure)
+    }
+
+    Ok(needs)
+}
+
+//Impls
+impl Descriptor_set_handle{
+    //Marks a set a draw does not bind
+    pub const NONE: Self = Descriptor_set_handle(u64::MAX);
+}
+
+impl Descriptor_allocator{
+    //Needs come from get_pool_needs for the layout's bindings
+    fn allocate(&mut self, device: &ash::Device, layout: vk::DescriptorSetLayout, needs: &[u32; POOL_TYPE_COUNT]) -> Vulkan_result<(vk::DescriptorSet, usize)>{
+        let layouts = [layout];
+
+        if let Some(index) = self.pools.iter().position(|pool| pool.fits(needs)) {
+            let alloc_info = vk::DescriptorSetAllocateInfo::default()
+                .descriptor_pool(self.pools[index].pool)
+                .set_layouts(&layouts);
+
+            //Freed sets can leave the pool fragmented even with enough descriptors left
+            match unsafe { device.allocate_descriptor_sets(&alloc_info) } {
+                Ok(sets) => {
+                    self.pools[index].take(needs);
+                    return Ok((sets[0], index))
+                },
+                Err(vk::Result::ERROR_FRAGMENTED_POOL) => (),
+                Err(error) => Err(error)?,
+            }
+        }
+
+        let pool = Descriptor_pool::new(device)?;
+        self.pools.push(pool);
+
+        let index = self.pools.len() - 1;
+        let alloc_info = vk::DescriptorSetAllocateInfo::default()
+            .descriptor_pool(self.pools[index].pool)
+            .set_layouts(&layouts);
+
+        let set = unsafe { device.allocate_descriptor_sets(&alloc_info)? }[0];
+        self.pools[index].take(needs);
+
+        Ok((set, index))
+    }
+
+    fn free(&mut self, device: &ash::Device, set: vk::DescriptorSet, pool: usize, needs: &[u32; POOL_TYPE_COUNT]){
+        let pool = &mut self.pools[pool];
+        unsafe {
+            let _ = device.free_descriptor_sets(pool.pool, &[set]);
+        }
+
+        pool.free_sets += 1;
+        pool.free_descriptors.iter_mut().zip(needs).for_each(|(free, need)| *free += need);
+    }
+
+    fn destroy(&mut self, device: &ash::Device){
+        unsafe {
+            self.pools.drain(..).for_each(|pool| device.destroy_descriptor_pool(pool.pool, None));
+        }
+    }
+}
+
+impl Descriptor_pool{
+    fn new(device: &ash::Device) -> Vulkan_result<Self>{
+        let pool_sizes = POOL_DESCRIPTOR_TYPES.iter().map(|&(descriptor_type, count)| {
+            vk::DescriptorPoolSize::default()
+                .ty(descriptor_type)
+                .descriptor_count(count * POOL_SET_COUNT)
+        }).collect::<Vec<_>>();
+
+        let create_info = vk::DescriptorPoolCreateInfo::default()
+            .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+            .max_sets(POOL_SET_COUNT)
+            .pool_sizes(&pool_sizes);
+
+        let pool = unsafe { device.create_descriptor_pool(&create_info, None)? };
+
+        save_log!(Log_level::General, Level::Info, "Successfully created descriptor pool");
+
+        Ok(Descriptor_pool{
+            pool,
+            free_sets: POOL_SET_COUNT,
+            free_descriptors: POOL_DESCRIPTOR_TYPES.map(|(_, count)| count * POOL_SET_COUNT),
+        })
+    }
+
+    fn fits(&self, needs: &[u32; POOL_TYPE_COUNT]) -> bool{
+        self.free_sets > 0 && self.free_descriptors.iter().zip(needs).all(|(free, need)| free >= need)
+    }
+
+    fn take(&mut self, needs: &[u32; POOL_TYPE_COUNT]){
+        self.free_sets -= 1;
+        self.free_descriptors.iter_mut().zip(needs).for_each(|(free, need)| *free -= need);
+    }
+}
+
+impl Descriptor_set{
+    pub(crate) fn get_set(&self, frame: usize) -> vk::DescriptorSet{
+        self.sets[frame].0
+    }
+
+    //The first binding that has not been written yet
+    pub(crate) fn get_unwritten_binding(&self) -> Option<u32>{
+        self.written.iter().position(|&written| !written).map(|index| self.bindings[index].binding)
+    }
+}
+
+impl Descriptor_sets{
+    //Bindings are the ones reflected for the set index, the layout is the pipeline's layout for it
+    #[allow(unused_variables)]
+    pub(crate) fn create(&mut self, device: &ash::Device, set: u32, layout: vk::DescriptorSetLayout, bindings: Vec<Descriptor_binding>, frame_count: usize) -> Vulkan_result<Descriptor_set_handle>{
+        let needs = get_pool_needs(&bindings)?;
+
+        let mut sets = vec![];
+        for _ in 0..frame_count {
+            match self.allocator.allocate(device, layout, &needs) {
+                Ok(allocated) => sets.push(allocated),
+                Err(code) => {
+                    sets.into_iter().for_each(|(descriptor_set, pool)| self.allocator.free(device, descriptor_set, pool, &needs));
+                    return Err(code)
+                }
+            }
+        }
+
+        let handle = Descriptor_set_handle(self.sets.len() as u64);
+        self.sets.push(Some(Descriptor_set{
+            sets,
+            #[cfg(all(debug_assertions, feature = "hot_reload"))]
+            layout,
+            pool_needs: needs,
+            written: vec![false; bindings.len()],
+            bindings,
+        }));
+
+        save_log!(Log_level::General, Level::Info, "Created descriptor set {} for set {}", handle.0, set);
+
+        Ok(handle)
+    }
+
+    pub(crate) fn get(&self, handle: Descriptor_set_handle) -> Option<&Descriptor_set>{
+        self.sets.get(handle.0 as usize)?.as_ref()
+    }
+
+    //Each frame's set points at that frame's buffer
+    //The caller must make sure the device no longer uses the set
+    #[allow(unused_variables)]
+    pub(crate) fn write_uniform_buffer(&mut self, device: &ash::Device, handle: Descriptor_set_handle, binding: u32, uniform_buffer: &Uniform_buffer) -> Vulkan_result<()>{
+        let index = self.find_binding(handle, binding, vk::DescriptorType::UNIFORM_BUFFER)?;
+        let descriptor_set = self.sets[handle.0 as usize].as_mut().unwrap();
+
+        let buffer_infos = (0..descriptor_set.sets.len()).map(|frame| [
+            vk::DescriptorBufferInfo::default()
+                .buffer(uniform_buffer.get_buffer(frame))
+                .offset(0)
+                .range(uniform_buffer.get_size())
+        ]).collect::<Vec<_>>();
+
+        let writes = descriptor_set.sets.iter().zip(buffer_infos.iter()).map(|(&(set, _), buffer_info)| {
+            vk::WriteDescriptorSet::default()
+                .dst_set(set)
+                .dst_binding(binding)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                .buffer_info(buffer_info)
+        }).collect::<Vec<_>>();
+
+        unsafe { device.update_descriptor_sets(&writes, &[]) };
+        descriptor_set.written[index] = true;
+
+        Ok(())
+    }
+
+    //Every frame's set samples the same texture
+    //The caller must make sure the device no longer uses the set
+    pub(crate) fn write_texture(&mut self, device: &ash::Device, handle: Descriptor_set_handle, binding: u32, view: vk::ImageView, sampler: vk::Sampler) -> Vulkan_result<()>{
+        let index = self.find_binding(handle, binding, vk::DescriptorType::COMBINED_IMAGE_SAMPLER)?;
+        let descriptor_set = self.sets[handle.0 as usize].as_mut().unwrap();
+
+        let image_info = [
+            vk::DescriptorImageInfo::default()
+                .image_view(view)
+                .sampler(sampler)
+                .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+        ];
+
+        let writes = descriptor_set.sets.iter().map(|&(set, _)| {
+            vk::WriteDescriptorSet::default()
+                .dst_set(set)
+                .dst_binding(binding)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .image_info(&image_info)
+        }).collect::<Vec<_>>();
+
+        unsafe { device.update_descriptor_sets(&writes, &[]) };
+        descriptor_set.written[index] = true;
+
+        Ok(())
+    }
+
+    //The caller must make sure the device no longer uses the set
+    pub(crate) fn destroy(&mut self, device: &ash::Device, handle: Descriptor_set_handle) -> Option<()>{
+        let descriptor_set = self.sets.get_mut(handle.0 as usize)?.take()?;
+        descriptor_set.sets.into_iter().for_each(|(set, pool)| self.allocator.free(device, set, pool, &descriptor_set.pool_needs));
+
+        Some(())
+    }
+
+    //Sets of a layout about to be destroyed could no longer be written, so their handles become unknown
+    //The caller must make sure the device no longer uses the sets
+    #[cfg(all(debug_assertions, feature = "hot_reload"))]
+    #[allow(unused_variables)]
+    pub(crate) fn invalidate_layouts(&mut self, device: &ash::Device, layouts: &[vk::DescriptorSetLayout]){
+        for (index, slot) in self.sets.iter_mut().enumerate() {
+            let Some(descriptor_set) = slot.take_if(|descriptor_set| layouts.contains(&descriptor_set.layout)) else {
+                continue
+            };
+            descriptor_set.sets.into_iter().for_each(|(set, pool)| self.allocator.free(device, set, pool, &descriptor_set.pool_needs));
+
+            save_log!(Log_level::General, Level::Warn, "Descriptor set {} belonged to a reloaded pipeline and has to be created again", index);
+        }
+    }
+
+    //Sets go away with their pools
+    pub(crate) fn destroy_all(&mut self, device: &ash::Device){
+        self.sets.clear();
+        self.allocator.destroy(device);
+    }
+
+    //Index of the binding within the set
+    #[allow(unused_variables)]
+    fn find_binding(&self, handle: Descriptor_set_handle, binding: u32, descriptor_type: vk::DescriptorType) -> Vulkan_result<usize>{
+        let Some(descriptor_set) = self.get(handle) else {
+            save_log!(Log_level::General, Level::Error, "Unknown descriptor set {}", handle.0);
+            return Err(Status_code::Failure)
+        };
+
+        match descriptor_set.bindings.iter().position(|existing| existing.binding == binding) {
+            Some(index) if descriptor_set.bindings[index].descriptor_type == descriptor_type => Ok(index),
+            Some(index) => {
+                save_log!(Log_level::General, Level::Error, "Binding {} of descriptor set {} is a {:?}, not a {:?}", binding, handle.0, descriptor_set.bindings[index].descriptor_type, descriptor_type);
+                Err(Status_code::Failure)
+            },
+            None => {
+                save_log!(Log_level::General, Level::Error, "Descriptor set {} has no binding {}", handle.0, binding);
+                Err(Status_code::Failure)
+            }
+        }
+    }
+}
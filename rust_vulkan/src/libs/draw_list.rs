@@ -0,0 +1,236 @@
+//Made by Han_feng
+
+use std::fmt::{Display, Formatter};
+use ash::vk;
+use crate::libs::descriptors::{Descriptor_set_handle, Descriptor_sets};
+use crate::libs::index_buffers::{Index_buffer_handle, Index_buffers};
+use crate::libs::pipelines::{Graphics_pipelines, Pipeline_handle};
+use crate::libs::shader_reflection::Push_constant_range;
+use crate::libs::vertex_buffers::{Vertex_buffer_handle, Vertex_buffers, Vertex_layout};
+
+//Consts
+pub const MAX_DRAW_DESCRIPTOR_SETS: usize = 4;
+
+//Enums
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Draw_error{
+    Unknown_pipeline(Pipeline_handle),
+    Unknown_vertex_buffer(Vertex_buffer_handle),
+    Unknown_index_buffer(Index_buffer_handle),
+    Layout_mismatch{pipeline: Pipeline_handle, vertex_buffer: Vertex_buffer_handle},
+    Missing_instance_buffer(Pipeline_handle),
+    Unexpected_instance_buffer(Pipeline_handle),
+    Out_of_range{first: u32, count: u32, available: u32},
+    Unknown_descriptor_set(Descriptor_set_handle),
+    Descriptor_set_mismatch{pipeline: Pipeline_handle, set: u32, descriptor_set: Descriptor_set_handle},
+    Missing_descriptor_set{pipeline: Pipeline_handle, set: u32},
+    Unwritten_binding{descriptor_set: Descriptor_set_handle, binding: u32},
+    //An expected size of zero means the pipeline has no push constants
+    Push_constant_size{pipeline: Pipeline_handle, expected: u32, size: u32},
+    Push_constants_out_of_range{offset: u32, size: u32, available: u32},
+}
+
+//Structs
+//One draw call of a frame, index_buffer and instance_buffer are NONE when unused
+//A count of zero draws the whole vertex or index buffer, an instance_count of zero every instance in the
+//instance buffer, or a single one without it
+//Descriptor sets are bound at their index in descriptor_sets, every set the pipeline uses has to be given
+//Push constants are push_constant_size bytes from push_constant_offset of the frame's push constant data,
+//covering the pipeline's whole range, or none when the pipeline has no push constants
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub struct Draw_command{
+    pub pipeline: Pipeline_handle,
+    pub vertex_buffer: Vertex_buffer_handle,
+    pub index_buffer: Index_buffer_handle,
+    pub instance_buffer: Vertex_buffer_handle,
+    //First vertex, or first index when indexed
+    pub first: u32,
+    pub count: u32,
+    //Added to every index
+    pub vertex_offset: i32,
+    pub first_instance: u32,
+    pub instance_count: u32,
+    pub descriptor_sets: [Descriptor_set_handle; MAX_DRAW_DESCRIPTOR_SETS],
+    pub push_constant_offset: u32,
+    pub push_constant_size: u32,
+}
+
+//A draw command resolved against the buffers and pipelines it refers to
+pub(crate) struct Draw{
+    pub(crate) pipeline: vk::Pipeline,
+    pub(crate) layout: vk::PipelineLayout,
+    //Set index and the set of the frame being recorded
+    pub(crate) descriptor_sets: Vec<(u32, vk::DescriptorSet)>,
+    //Stages, offset and bytes
+    pub(crate) push_constants: Option<(vk::ShaderStageFlags, u32, Vec<u8>)>,
+    pub(crate) vertex_buffers: Vec<vk::Buffer>,
+    pub(crate) index_buffer: Option<(vk::Buffer, vk::IndexType)>,
+    pub(crate) first: u32,
+    pub(crate) count: u32,
+    pub(crate) vertex_offset: i32,
+    pub(crate) first_instance: u32,
+    pub(crate) instance_count: u32,
+}
+
+//Functions
+//Elements a draw covers out of those available, a count of zero takes everything from first
+pub fn get_draw_range(first: u32, count: u32, available: u32) -> Result<(u32, u32), Draw_error>{
+    let count = if count == 0 { available.saturating_sub(first) } else { count };
+    if first.checked_add(count).is_none_or(|end| end > available) {
+        return Err(Draw_error::Out_of_range{first, count, available})
+    }
+
+    Ok((first, count))
+}
+
+//Impls
+impl Draw_command{
+    pub fn new(pipeline: Pipeline_handle, vertex_buffer: Vertex_buffer_handle) -> Self{
+        Draw_command{
+            pipeline, vertex_buffer,
+            index_buffer: Index_buffer_handle::NONE,
+            instance_buffer: Vertex_buffer_handle::NONE,
+            first: 0,
+            count: 0,
+            vertex_offset: 0,
+            first_instance: 0,
+            instance_count: 0,
+            descriptor_sets: [Descriptor_set_handle::NONE; MAX_DRAW_DESCRIPTOR_SETS],
+            push_constant_offset: 0,
+            push_constant_size: 0,
+        }
+    }
+
+    //None while the pipeline is not ready, such draws are skipped instead of stalling the frame
+    pub(crate) fn resolve(&self, graphics_pipelines: &Graphics_pipelines, vertex_buffers: &Vertex_buffers, index_buffers: &Index_buffers, descriptor_sets: &Descriptor_sets, push_constants: &[u8], frame: usize) -> Result<Option<Draw>, Draw_error>{
+        let (vertex_layout, instance_layout) = graphics_pipelines.get_vertex_layouts(self.pipeline).ok_or(Draw_error::Unknown_pipeline(self.pipeline))?;
+        let interface = graphics_pipelines.get_interface(self.pipeline).ok_or(Draw_error::Unknown_pipeline(self.pipeline))?;
+        let vertex_buffer = vertex_buffers.get(self.vertex_buffer).ok_or(Draw_error::Unknown_vertex_buffer(self.vertex_buffer))?;
+        let mismatch = |layout: Option<&Vertex_layout>, buffer_layout: &Vertex_layout, vertex_buffer| match layout {
+            Some(layout) if layout != buffer_layout => Err(Draw_error::Layout_mismatch{pipeline: self.pipeline, vertex_buffer}),
+            _ => Ok(()),
+        };
+        mismatch(vertex_layout, &vertex_buffer.layout, self.vertex_buffer)?;
+
+        let mut buffers = vec![vertex_buffer.buffer.buffer];
+        let (first_instance, instance_count) = match (instance_layout, self.instance_buffer) {
+            (None, Vertex_buffer_handle::NONE) => (self.first_instance, self.instance_count.max(1)),
+            (None, _) => return Err(Draw_error::Unexpected_instance_buffer(self.pipeline)),
+            (Some(_), Vertex_buffer_handle::NONE) => return Err(Draw_error::Missing_instance_buffer(self.pipeline)),
+            (Some(layout), handle) => {
+                let instance_buffer = vertex_buffers.get(handle).ok_or(Draw_error::Unknown_vertex_buffer(handle))?;
+                mismatch(Some(layout), &instance_buffer.layout, handle)?;
+                buffers.push(instance_buffer.buffer.buffer);
+
+                get_draw_range(self.first_instance, self.instance_count, instance_buffer.vertex_count)?
+            },
+        };
+
+        let (index_buffer, (first, count)) = match self.index_buffer {
+            Index_buffer_handle::NONE => (None, get_draw_range(self.first, self.count, vertex_buffer.vertex_count)?),
+            handle => {
+                let index_buffer = index_buffers.get(handle).ok_or(Draw_error::Unknown_index_buffer(handle))?;
+
+                (Some((index_buffer.buffer.buffer, index_buffer.index_type.into())), get_draw_range(self.first, self.count, index_buffer.index_count)?)
+            },
+        };
+
+        //Every slot is checked, a set given where the pipeline uses none is a mismatch
+        let mut sets = vec![];
+        for set in 0..MAX_DRAW_DESCRIPTOR_SETS as u32 {
+            let bindings = interface.get_set_bindings(set);
+            match self.descriptor_sets.get(set as usize).copied().unwrap_or(Descriptor_set_handle::NONE) {
+                Descriptor_set_handle::NONE if bindings.is_empty() => (),
+                Descriptor_set_handle::NONE => return Err(Draw_error::Missing_descriptor_set{pipeline: self.pipeline, set}),
+                handle => {
+                    let descriptor_set = descriptor_sets.get(handle).ok_or(Draw_error::Unknown_descriptor_set(handle))?;
+                    if descriptor_set.bindings != bindings {
+                        return Err(Draw_error::Descriptor_set_mismatch{pipeline: self.pipeline, set, descriptor_set: handle})
+                    }
+                    if let Some(binding) = descriptor_set.get_unwritten_binding() {
+                        return Err(Draw_error::Unwritten_binding{descriptor_set: handle, binding})
+                    }
+
+                    sets.push((set, descriptor_set.get_set(frame)));
+                },
+            }
+        }
+
+        let push_constants = self.get_push_constants(interface.push_constants, push_constants)?
+            .zip(interface.push_constants)
+            .map(|(bytes, range)| (range.stages, range.offset, bytes.to_vec()));
+
+        Ok(graphics_pipelines.get(self.pipeline).map(|pipeline| Draw{
+            pipeline, index_buffer, push_constants,
+            layout: interface.layout,
+            descriptor_sets: sets, first, count, first_instance, instance_count,
+            vertex_buffers: buffers,
+            vertex_offset: self.vertex_offset,
+        }))
+    }
+
+    //The bytes this draw pushes out of the frame's push constant data, checked against the pipeline's range
+    pub fn get_push_constants<'a>(&self, range: Option<Push_constant_range>, push_constants: &'a [u8]) -> Result<Option<&'a [u8]>, Draw_error>{
+        let expected = range.map_or(0, |range| range.size);
+        if self.push_constant_size != expected {
+            return Err(Draw_error::Push_constant_size{pipeline: self.pipeline, expected, size: self.push_constant_size})
+        }
+        if range.is_none() {
+            return Ok(None)
+        }
+
+        let (offset, size) = (self.push_constant_offset, self.push_constant_size);
+        let bytes = push_constants.get(offset as usize..offset as usize + size as usize)
+            .ok_or(Draw_error::Push_constants_out_of_range{offset, size, available: push_constants.len() as u32})?;
+
+        Ok(Some(bytes))
+    }
+}
+
+impl Draw{
+    pub(crate) fn record(&self, device: &ash::Device, command_buffer: vk::CommandBuffer){
+        let offsets = vec![0; self.vertex_buffers.len()];
+
+        unsafe {
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &self.vertex_buffers, &offsets);
+            self.descriptor_sets.iter().for_each(|&(set, descriptor_set)| {
+                device.cmd_bind_descriptor_sets(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.layout, set, &[descriptor_set], &[]);
+            });
+            if let Some((stages, offset, bytes)) = &self.push_constants {
+                device.cmd_push_constants(command_buffer, self.layout, *stages, *offset, bytes);
+            }
+
+            match self.index_buffer {
+                Some((buffer, index_type)) => {
+                    device.cmd_bind_index_buffer(command_buffer, buffer, 0, index_type);
+                    device.cmd_draw_indexed(command_buffer, self.count, self.instance_count, self.first, self.vertex_offset, self.first_instance);
+                },
+                None => device.cmd_draw(command_buffer, self.count, self.instance_count, self.first, self.first_instance),
+            }
+        }
+    }
+}
+
+impl Display for Draw_error{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unknown_pipeline(handle) => write!(f, "unknown pipeline {}", handle.0),
+            Self::Unknown_vertex_buffer(handle) => write!(f, "unknown vertex buffer {}", handle.0),
+            Self::Unknown_index_buffer(handle) => write!(f, "unknown index buffer {}", handle.0),
+            Self::Layout_mismatch{pipeline, vertex_buffer} => write!(f, "vertex buffer {} does not match the layout of pipeline {}", vertex_buffer.0, pipeline.0),
+            Self::Missing_instance_buffer(handle) => write!(f, "pipeline {} needs an instance buffer", handle.0),
+            Self::Unexpected_instance_buffer(handle) => write!(f, "pipeline {} has no instance layout", handle.0),
+            Self::Out_of_range{first, count, available} => write!(f, "{} elements from {} exceed the {} in the buffer", count, first, available),
+            Self::Unknown_descriptor_set(handle) => write!(f, "unknown descriptor set {}", handle.0),
+            Self::Descriptor_set_mismatch{pipeline, set, descriptor_set} => write!(f, "descriptor set {} does not match set {} of pipeline {}", descriptor_set.0, set, pipeline.0),
+            Self::Missing_descriptor_set{pipeline, set} => write!(f, "pipeline {} needs a descriptor set at {}", pipeline.0, set),
+            Self::Unwritten_binding{descriptor_set, binding} => write!(f, "binding {} of descriptor set {} was never written", binding, descriptor_set.0),
+            Self::Push_constant_size{pipeline, expected, size} => write!(f, "pipeline {} takes {} bytes of push constants, got {}", pipeline.0, expected, size),
+            Self::Push_constants_out_of_range{offset, size, available} => write!(f, "{} push constant bytes from {} exceed the {} given for the frame", size, offset, available),
+        }
+    }
+}
+
+impl std::error::Error for Draw_error{}
@@ -0,0 +1,217 @@
+//Made by Han_feng
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+use log::Level;
+use serde::Deserialize;
+use shaderc::{CompileOptions, Compiler, IncludeCallbackResult, IncludeType, OptimizationLevel, ResolvedInclude, ShaderKind};
+use crate::libs::shaders::{Shader_code, Shader_stage};
+use crate::libs::utils::{Log_level, Status_code, Vulkan_result};
+use crate::save_log;
+
+//Consts
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+//Overrides the shader directory the crate was built from
+const SHADER_DIR_VARIABLE: &str = "RUST_VULKAN_SHADER_DIR";
+const DEFAULT_SHADER_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders");
+const SHADER_INCLUDE_DIR: &str = "include";
+
+//Only stages a graphics pipeline can be built from are reloaded
+const SHADER_KINDS: &[(&str, ShaderKind, Shader_stage)] = &[
+    ("vert", ShaderKind::Vertex, Shader_stage::Vertex),
+    ("frag", ShaderKind::Fragment, Shader_stage::Fragment),
+    ("geom", ShaderKind::Geometry, Shader_stage::Geometry),
+    ("tesc", ShaderKind::TessControl, Shader_stage::Tess_control),
+    ("tese", ShaderKind::TessEvaluation, Shader_stage::Tess_evaluation),
+];
+
+//Structs
+//Polls the shader directory on a thread of its own, edited sources are picked up between frames
+pub(crate) struct Shader_watcher{
+    shader_dir: PathBuf,
+    receiver: mpsc::Receiver<Vec<Shader_source>>,
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Shader_source{
+    pub(crate) name: String,
+    pub(crate) stage: Shader_stage,
+    path: PathBuf,
+    kind: ShaderKind,
+}
+
+//The part of the build.rs sidecar that affects compilation, everything else is ignored
+#[derive(Default, Deserialize)]
+struct Shader_description{
+    #[serde(default)]
+    defines: BTreeMap<String, Option<String>>,
+    #[serde(default)]
+    features: Vec<String>,
+}
+
+//Impls
+impl Shader_watcher{
+    pub(crate) fn new() -> Vulkan_result<Self>{
+        let shader_dir = std::env::var_os(SHADER_DIR_VARIABLE).map(PathBuf::from).unwrap_or_else(|| PathBuf::from(DEFAULT_SHADER_DIR));
+        if !shader_dir.is_dir() {
+            save_log!(Log_level::General, Level::Error, "Shader directory {} does not exist", shader_dir.display());
+            return Err(Status_code::Failure)
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let (stop, stop_receiver) = mpsc::channel::<()>();
+
+        let thread = {
+            let shader_dir = shader_dir.clone();
+            let mut modified = get_modified_times(&shader_dir);
+
+            std::thread::Builder::new()
+                .name("shader_watcher".to_string())
+                .spawn(move || {
+                    //Dropping the stop sender ends the loop as well
+                    while let Err(mpsc::RecvTimeoutError::Timeout) = stop_receiver.recv_timeout(POLL_INTERVAL) {
+                        let current = get_modified_times(&shader_dir);
+                        let changed = get_changed_sources(&modified, &current);
+                        modified = current;
+
+                        if !changed.is_empty() && sender.send(changed).is_err() {
+                            break
+                        }
+                    }
+                })?
+        };
+
+        save_log!(Log_level::General, Level::Info, "Watching shaders in {}", shader_dir.display());
+
+        Ok(Shader_watcher{
+            shader_dir, receiver,
+            stop: Some(stop),
+            thread: Some(thread),
+        })
+    }
+
+    //Sources changed since the last call, each one at most once
+    pub(crate) fn poll(&self) -> Vec<Shader_source>{
+        self.receiver.try_iter().flatten()
+            .map(|source| (source.path.clone(), source))
+            .collect::<BTreeMap<_, _>>()
+            .into_values()
+            .collect()
+    }
+
+    //Features the shader does not declare are left undefined, the same way build.rs compiles variants
+    pub(crate) fn compile(&self, source: &Shader_source, features: &[String]) -> Result<Shader_code, String>{
+        let text = fs::read_to_string(&source.path).map_err(|error| format!("{}: {}", source.path.display(), error))?;
+        let description = get_shader_description(&source.path)?;
+
+        let compiler = Compiler::new().map_err(|error| error.to_string())?;
+        let mut compile_options = CompileOptions::new().map_err(|error| error.to_string())?;
+        compile_options.set_optimization_level(OptimizationLevel::Zero);
+        compile_options.set_generate_debug_info();
+
+        for (name, value) in &description.defines {
+            compile_options.add_macro_definition(name, value.as_deref());
+        }
+        for feature in features.iter().filter(|feature| description.features.contains(feature)) {
+            compile_options.add_macro_definition(feature, Some("1"));
+        }
+
+        let shader_dir = self.shader_dir.clone();
+        compile_options.set_include_callback(move |requested, include_type, requesting, _| resolve_include(&shader_dir, requested, include_type, requesting));
+
+        let artifact = compiler.compile_into_spirv(&text, source.kind, &source.path.to_string_lossy(), "main", Some(&compile_options))
+            .map_err(|error| error.to_string())?;
+
+        Ok(artifact.as_binary().into())
+    }
+}
+
+impl Drop for Shader_watcher{
+    fn drop(&mut self) {
+        self.stop = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Shader_source{
+    //name.<stage> and name.<stage>.glsl, the same names build.rs registers
+    fn new(path: &Path) -> Option<Self>{
+        let (stem, extension) = match path.extension()?.to_str()? {
+            "glsl" => {
+                let stem = Path::new(path.file_stem()?);
+                (stem.file_stem()?, stem.extension()?.to_str()?)
+            },
+            extension => (path.file_stem()?, extension),
+        };
+        let (_, kind, stage) = SHADER_KINDS.iter().find(|(kind_extension, ..)| *kind_extension == extension)?;
+
+        Some(Shader_source{
+            name: stem.to_str()?.to_string(),
+            stage: *stage,
+            path: path.to_path_buf(),
+            kind: *kind,
+        })
+    }
+}
+
+//Functions
+fn get_modified_times(shader_dir: &Path) -> HashMap<PathBuf, SystemTime>{
+    [shader_dir.to_path_buf(), shader_dir.join(SHADER_INCLUDE_DIR)].iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| Some((entry.path(), entry.metadata().ok()?.modified().ok()?)))
+        .collect()
+}
+
+//A changed source or sidecar reloads its shader, any other changed file may be included anywhere so it reloads every shader
+fn get_changed_sources(previous: &HashMap<PathBuf, SystemTime>, current: &HashMap<PathBuf, SystemTime>) -> Vec<Shader_source>{
+    let changed = current.iter()
+        .filter(|(path, modified)| previous.get(*path) != Some(*modified))
+        .map(|(path, _)| path)
+        .collect::<Vec<_>>();
+
+    let sources = current.keys().filter_map(|path| Shader_source::new(path)).collect::<Vec<_>>();
+    let belongs_to = |path: &Path, source: &Shader_source| path == source.path || path.with_extension("") == source.path;
+
+    let shared_changed = changed.iter().any(|path| !sources.iter().any(|source| belongs_to(path, source)));
+
+    sources.into_iter().filter(|source| shared_changed || changed.iter().any(|path| belongs_to(path, source))).collect()
+}
+
+fn get_shader_description(path: &Path) -> Result<Shader_description, String>{
+    let mut description_path = path.as_os_str().to_owned();
+    description_path.push(".json");
+
+    match fs::read_to_string(&description_path) {
+        Ok(source) => serde_json::from_str(&source).map_err(|error| format!("{}: {}", Path::new(&description_path).display(), error)),
+        Err(_) => Ok(Shader_description::default()),
+    }
+}
+
+fn resolve_include(shader_dir: &Path, requested: &str, include_type: IncludeType, requesting: &str) -> IncludeCallbackResult{
+    let mut candidates = vec![];
+    if matches!(include_type, IncludeType::Relative) {
+        candidates.push(Path::new(requesting).parent().unwrap_or(Path::new("")).join(requested));
+    }
+    candidates.push(shader_dir.join(requested));
+    candidates.push(shader_dir.join(SHADER_INCLUDE_DIR).join(requested));
+
+    let path = candidates.into_iter().find(|candidate| candidate.is_file())
+        .ok_or_else(|| format!("cannot find {} included from {}", requested, requesting))?;
+    let content = fs::read_to_string(&path).map_err(|error| format!("{}: {}", path.display(), error))?;
+
+    Ok(ResolvedInclude{
+        resolved_name: path.to_string_lossy().into_owned(),
+        content,
+    })
+}
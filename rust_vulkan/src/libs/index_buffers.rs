@@ -0,0 +1,143 @@
+//Made by Han_feng
+
+use ash::vk;
+use crate::libs::buffers::Buffer;
+use crate::libs::memory_allocator::Memory_allocator;
+use crate::libs::utils::{Status_code, Vulkan_result};
+use crate::save_log;
+
+cfg_if::cfg_if! {
+    if #[cfg(debug_assertions)] {
+        use crate::libs::utils::Log_level;
+        use log::Level;
+    }
+}
+
+//Enums
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub enum Index_type{
+    U16 = 0,
+    U32 = 1,
+}
+
+//Structs
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Index_buffer_handle(pub u64);
+
+//Indices are not checked against the vertex buffers they are drawn with
+pub(crate) struct Index_buffer{
+    pub(crate) buffer: Buffer,
+    pub(crate) index_type: Index_type,
+    pub(crate) index_count: u32,
+}
+
+//Destroyed buffers leave an empty slot behind so handles stay stable
+#[derive(Default)]
+pub(crate) struct Index_buffers{
+    buffers: Vec<Option<Index_buffer>>,
+}
+
+//Traits
+//Sealed, the index bytes are read straight from memory, which is only sound for u16 and u32
+pub trait Index: Copy + sealed::Sealed{
+    const TYPE: Index_type;
+}
+
+//Modules
+mod sealed{
+    pub trait Sealed{}
+
+    impl Sealed for u16{}
+    impl Sealed for u32{}
+}
+
+//Functions
+pub(crate) fn get_index_bytes<I: Index>(indices: &[I]) -> &[u8]{
+    unsafe { std::slice::from_raw_parts(indices.as_ptr() as *const u8, size_of_val(indices)) }
+}
+
+//Impls
+impl Index_type{
+    pub fn get_size(&self) -> u32{
+        match self {
+            Self::U16 => 2,
+            Self::U32 => 4,
+        }
+    }
+}
+
+impl TryFrom<u32> for Index_type{
+    type Error = Status_code;
+
+    #[allow(unused_variables)]
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::U16),
+            1 => Ok(Self::U32),
+            _ => {
+                save_log!(Log_level::General, Level::Error, "Unknown index type {}", value);
+                Err(Status_code::Failure)
+            }
+        }
+    }
+}
+
+impl From<Index_type> for vk::IndexType{
+    fn from(index_type: Index_type) -> Self {
+        match index_type {
+            Index_type::U16 => vk::IndexType::UINT16,
+            Index_type::U32 => vk::IndexType::UINT32,
+        }
+    }
+}
+
+impl Index for u16{
+    const TYPE: Index_type = Index_type::U16;
+}
+
+impl Index for u32{
+    const TYPE: Index_type = Index_type::U32;
+}
+
+impl Index_buffer_handle{
+    //Marks a draw without indices
+    pub const NONE: Self = Index_buffer_handle(u64::MAX);
+}
+
+impl Index_buffers{
+    pub(crate) fn create(&mut self, device: &ash::Device, allocator: &mut Memory_allocator, pool: vk::CommandPool, queue: vk::Queue, index_type: Index_type, data: &[u8]) -> Vulkan_result<Index_buffer_handle>{
+        let size = index_type.get_size() as usize;
+        if data.is_empty() || data.len() % size != 0 {
+            save_log!(Log_level::General, Level::Error, "Failed to create index buffer: {} bytes are not a whole number of {:?} indices", data.len(), index_type);
+            return Err(Status_code::Failure)
+        }
+
+        let buffer = Buffer::new_device_local(device, allocator, pool, queue, data, vk::BufferUsageFlags::INDEX_BUFFER)?;
+        let handle = Index_buffer_handle(self.buffers.len() as u64);
+        let index_count = (data.len() / size) as u32;
+        self.buffers.push(Some(Index_buffer{
+            buffer, index_type, index_count
+        }));
+
+        save_log!(Log_level::General, Level::Info, "Created index buffer {} with {} indices", handle.0, index_count);
+
+        Ok(handle)
+    }
+
+    pub(crate) fn get(&self, handle: Index_buffer_handle) -> Option<&Index_buffer>{
+        self.buffers.get(handle.0 as usize)?.as_ref()
+    }
+
+    //The caller must make sure the device no longer uses the buffer
+    pub(crate) fn destroy(&mut self, device: &ash::Device, allocator: &mut Memory_allocator, handle: Index_buffer_handle) -> Option<()>{
+        self.buffers.get_mut(handle.0 as usize)?.take()?.buffer.destroy(device, allocator);
+
+        Some(())
+    }
+
+    pub(crate) fn destroy_all(&mut self, device: &ash::Device, allocator: &mut Memory_allocator){
+        self.buffers.drain(..).flatten().for_each(|buffer| buffer.buffer.destroy(device, allocator));
+    }
+}
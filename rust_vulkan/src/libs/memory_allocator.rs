@@ -0,0 +1,377 @@
+//Made by Han_feng
+
+use std::collections::BTreeMap;
+use std::ptr::NonNull;
+use ash::vk;
+use crate::libs::utils::{Status_code, Vulkan_result};
+use crate::save_log;
+
+cfg_if::cfg_if! {
+    if #[cfg(debug_assertions)] {
+        use crate::libs::utils::Log_level;
+        use log::Level;
+    }
+}
+
+//Consts
+const DEFAULT_BLOCK_SIZE: vk::DeviceSize = 64 << 20;
+//Small heaps get smaller blocks so a single block never takes more than this share of the heap
+const HEAP_BLOCK_FRACTION: vk::DeviceSize = 8;
+
+//Enums
+//Buffers and linear images never share a block with optimal images, so bufferImageGranularity never applies
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(C)]
+pub enum Pool_kind{
+    Linear = 0,
+    Optimal = 1,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Memory_usage{
+    //Only touched by the device, filled through a staging buffer
+    Gpu_only,
+    //Rewritten by the host every frame, device local when the device offers such host visible memory
+    Cpu_to_gpu,
+    //Written once by the host and copied from
+    Staging,
+}
+
+//Structs
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[repr(C)]
+pub struct Memory_stats{
+    pub block_count: u64,
+    pub allocation_count: u64,
+    //Device memory held by the blocks
+    pub reserved_bytes: u64,
+    //Part of it handed out to allocations, alignment padding excluded
+    pub used_bytes: u64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Pool_stats{
+    pub memory_type: u32,
+    pub kind: Pool_kind,
+    pub stats: Memory_stats,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Memory_report{
+    pub total: Memory_stats,
+    pub pools: Vec<Pool_stats>,
+}
+
+//Has to be given back through Memory_allocator::free
+pub(crate) struct Allocation{
+    pub(crate) memory: vk::DeviceMemory,
+    pub(crate) offset: vk::DeviceSize,
+    pub(crate) size: vk::DeviceSize,
+    mapped: Option<NonNull<u8>>,
+    pool: (u32, Pool_kind),
+    block: usize,
+}
+
+pub(crate) struct Memory_allocator{
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    pools: BTreeMap<(u32, Pool_kind), Memory_pool>,
+}
+
+struct Memory_pool{
+    block_size: vk::DeviceSize,
+    //Released blocks leave an empty slot behind so allocations keep pointing at the right one
+    blocks: Vec<Option<Memory_block>>,
+}
+
+struct Memory_block{
+    memory: vk::DeviceMemory,
+    //Host visible blocks stay mapped for their whole lifetime
+    mapped: Option<NonNull<u8>>,
+    ranges: Free_list,
+    //Holds a single allocation too large to share a block
+    dedicated: bool,
+}
+
+//Suballocates the range [0, size) of a block, kept apart from the device memory it describes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Free_list{
+    size: vk::DeviceSize,
+    //Offset and size of every free range, sorted and never adjacent
+    free_ranges: Vec<(vk::DeviceSize, vk::DeviceSize)>,
+    allocation_count: u64,
+}
+
+//Impls
+//The mapping belongs to the block, which only the allocator touches
+unsafe impl Send for Allocation{}
+unsafe impl Send for Memory_block{}
+
+impl Memory_usage{
+    //Required then preferred property flags
+    fn get_flags(&self) -> (vk::MemoryPropertyFlags, vk::MemoryPropertyFlags){
+        let host_visible = vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+
+        match self {
+            Self::Gpu_only => (vk::MemoryPropertyFlags::DEVICE_LOCAL, vk::MemoryPropertyFlags::empty()),
+            Self::Cpu_to_gpu => (host_visible, vk::MemoryPropertyFlags::DEVICE_LOCAL),
+            Self::Staging => (host_visible, vk::MemoryPropertyFlags::empty()),
+        }
+    }
+}
+
+impl Allocation{
+    //Only valid for allocations made with a host visible usage
+    #[allow(unused_variables)]
+    pub(crate) fn write(&self, offset: vk::DeviceSize, data: &[u8]) -> Vulkan_result<()>{
+        let Some(mapped) = self.mapped else {
+            save_log!(Log_level::General, Level::Error, "Failed to write allocation: memory is not host visible");
+            return Err(Status_code::Failure)
+        };
+
+        if offset.checked_add(data.len() as vk::DeviceSize).is_none_or(|end| end > self.size) {
+            save_log!(Log_level::General, Level::Error, "Failed to write allocation: {} bytes at {} exceed its {} bytes", data.len(), offset, self.size);
+            return Err(Status_code::Failure)
+        }
+
+        unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), mapped.as_ptr().add(offset as usize), data.len()) };
+
+        Ok(())
+    }
+}
+
+impl Memory_allocator{
+    pub(crate) fn new(memory_properties: vk::PhysicalDeviceMemoryProperties) -> Self{
+        Memory_allocator{
+            memory_properties,
+            pools: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn find_memory_type(&self, type_bits: u32, usage: Memory_usage) -> Option<u32>{
+        let (required, preferred) = usage.get_flags();
+        let find = |flags: vk::MemoryPropertyFlags| (0..self.memory_properties.memory_type_count).find(|&index| {
+            type_bits & (1 << index) != 0 && self.memory_properties.memory_types[index as usize].property_flags.contains(flags)
+        });
+
+        find(required | preferred).or_else(|| find(required))
+    }
+
+    #[allow(unused_variables)]
+    pub(crate) fn allocate(&mut self, device: &ash::Device, requirements: vk::MemoryRequirements, usage: Memory_usage, kind: Pool_kind) -> Vulkan_result<Allocation>{
+        let Some(memory_type) = self.find_memory_type(requirements.memory_type_bits, usage) else {
+            save_log!(Log_level::General, Level::Error, "Failed to find memory type for {:?}", usage);
+            return Err(Status_code::Failure)
+        };
+
+        let heap_size = self.memory_properties.memory_heaps[self.memory_properties.memory_types[memory_type as usize].heap_index as usize].size;
+        let pool = self.pools.entry((memory_type, kind)).or_insert_with(|| Memory_pool{
+            block_size: DEFAULT_BLOCK_SIZE.min(heap_size / HEAP_BLOCK_FRACTION).max(1),
+            blocks: vec![],
+        });
+
+        let size = requirements.size;
+        let alignment = requirements.alignment.max(1);
+
+        //Anything larger than half a block gets one of its own
+        let dedicated = size > pool.block_size / 2;
+        let found = pool.blocks.iter_mut().enumerate()
+            .filter(|_| !dedicated)
+            .filter_map(|(index, block)| Some((index, block.as_mut().filter(|block| !block.dedicated)?)))
+            .find_map(|(index, block)| Some((index, block.ranges.allocate(size, alignment)?)));
+
+        let (block, offset) = match found {
+            Some(found) => found,
+            None => {
+                let block = pool.push_block(device, &self.memory_properties, memory_type, if dedicated { size } else { pool.block_size }, dedicated)?;
+                let offset = pool.blocks[block].as_mut().unwrap().ranges.allocate(size, alignment).ok_or(Status_code::Failure)?;
+
+                (block, offset)
+            },
+        };
+
+        let memory_block = pool.blocks[block].as_ref().unwrap();
+
+        Ok(Allocation{
+            memory: memory_block.memory,
+            offset, size,
+            mapped: memory_block.mapped.map(|mapped| unsafe { mapped.add(offset as usize) }),
+            pool: (memory_type, kind),
+            block,
+        })
+    }
+
+    //An emptied block is released unless it is the last one its pool has left
+    pub(crate) fn free(&mut self, device: &ash::Device, allocation: Allocation){
+        let Some(pool) = self.pools.get_mut(&allocation.pool) else {
+            return
+        };
+        let Some(block) = pool.blocks.get_mut(allocation.block).and_then(|block| block.as_mut()) else {
+            return
+        };
+
+        block.ranges.free(allocation.offset, allocation.size);
+
+        let live_blocks = pool.blocks.iter().flatten().filter(|block| !block.dedicated).count();
+        let block = pool.blocks[allocation.block].as_ref().unwrap();
+        if block.ranges.allocation_count == 0 && (block.dedicated || live_blocks > 1) {
+            pool.blocks[allocation.block].take().unwrap().destroy(device);
+        }
+    }
+
+    pub(crate) fn get_report(&self) -> Memory_report{
+        let pools = self.pools.iter().map(|(&(memory_type, kind), pool)| Pool_stats{
+            memory_type, kind,
+            stats: pool.get_stats(),
+        }).collect::<Vec<_>>();
+
+        let total = pools.iter().map(|pool| pool.stats).sum();
+
+        Memory_report{
+            total, pools
+        }
+    }
+
+    //Every allocation should be back by the time the application goes away, live ones are logged per pool
+    #[allow(unused_variables)]
+    pub(crate) fn check_teardown(&self) -> bool{
+        let report = self.get_report();
+        report.pools.iter().filter(|pool| pool.stats.allocation_count > 0).for_each(|pool| {
+            save_log!(Log_level::General, Level::Error, "{} allocations ({} bytes) still alive at teardown in {:?} pool of memory type {}", pool.stats.allocation_count, pool.stats.used_bytes, pool.kind, pool.memory_type);
+        });
+
+        report.total.allocation_count == 0
+    }
+
+    pub(crate) fn destroy(&mut self, device: &ash::Device){
+        self.pools.values_mut().for_each(|pool| pool.blocks.drain(..).flatten().for_each(|block| block.destroy(device)));
+        self.pools.clear();
+    }
+}
+
+impl Memory_pool{
+    fn push_block(&mut self, device: &ash::Device, memory_properties: &vk::PhysicalDeviceMemoryProperties, memory_type: u32, size: vk::DeviceSize, dedicated: bool) -> Vulkan_result<usize>{
+        let block = Memory_block::new(device, memory_properties, memory_type, size, dedicated)?;
+
+        Ok(match self.blocks.iter().position(|block| block.is_none()) {
+            Some(index) => {
+                self.blocks[index] = Some(block);
+                index
+            },
+            None => {
+                self.blocks.push(Some(block));
+                self.blocks.len() - 1
+            }
+        })
+    }
+
+    fn get_stats(&self) -> Memory_stats{
+        self.blocks.iter().flatten().map(|block| block.ranges.get_stats()).sum()
+    }
+}
+
+impl Memory_block{
+    #[allow(unused_variables)]
+    fn new(device: &ash::Device, memory_properties: &vk::PhysicalDeviceMemoryProperties, memory_type: u32, size: vk::DeviceSize, dedicated: bool) -> Vulkan_result<Self>{
+        let alloc_info = vk::MemoryAllocateInfo::default()
+            .allocation_size(size)
+            .memory_type_index(memory_type);
+
+        let memory = unsafe { device.allocate_memory(&alloc_info, None)? };
+
+        let host_visible = memory_properties.memory_types[memory_type as usize].property_flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE);
+        let mapped = if host_visible {
+            match unsafe { device.map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty()) } {
+                Ok(mapped) => NonNull::new(mapped as *mut u8),
+                Err(error) => {
+                    unsafe { device.free_memory(memory, None) };
+                    Err(error)?
+                }
+            }
+        }
+        else {
+            None
+        };
+
+        save_log!(Log_level::General, Level::Info, "Allocated {}memory block of {} bytes from memory type {}", if dedicated { "dedicated " } else { "" }, size, memory_type);
+
+        Ok(Memory_block{
+            memory, mapped, dedicated,
+            ranges: Free_list::new(size),
+        })
+    }
+
+    fn destroy(self, device: &ash::Device){
+        unsafe {
+            if self.mapped.is_some() {
+                device.unmap_memory(self.memory);
+            }
+            device.free_memory(self.memory, None);
+        }
+    }
+}
+
+impl Free_list{
+    pub fn new(size: vk::DeviceSize) -> Self{
+        Free_list{
+            size,
+            free_ranges: vec![(0, size)],
+            allocation_count: 0,
+        }
+    }
+
+    //First fit, the padding in front of an aligned offset stays free
+    pub fn allocate(&mut self, size: vk::DeviceSize, alignment: vk::DeviceSize) -> Option<vk::DeviceSize>{
+        let (index, offset) = self.free_ranges.iter().enumerate().find_map(|(index, &(start, length))| {
+            let offset = start.next_multiple_of(alignment.max(1));
+            (offset + size <= start + length).then_some((index, offset))
+        })?;
+
+        let (start, length) = self.free_ranges.remove(index);
+        let remaining = [(start, offset - start), (offset + size, start + length - offset - size)];
+        remaining.into_iter().rev().filter(|&(_, length)| length > 0).for_each(|range| self.free_ranges.insert(index, range));
+
+        self.allocation_count += 1;
+
+        Some(offset)
+    }
+
+    pub fn free(&mut self, offset: vk::DeviceSize, size: vk::DeviceSize){
+        self.allocation_count = self.allocation_count.saturating_sub(1);
+
+        let index = self.free_ranges.partition_point(|&(start, _)| start < offset);
+        self.free_ranges.insert(index, (offset, size));
+
+        //Merge with the following range, then with the preceding one
+        if index + 1 < self.free_ranges.len() && offset + size == self.free_ranges[index + 1].0 {
+            self.free_ranges[index].1 += self.free_ranges.remove(index + 1).1;
+        }
+        if index > 0 && self.free_ranges[index - 1].0 + self.free_ranges[index - 1].1 == offset {
+            self.free_ranges[index - 1].1 += self.free_ranges.remove(index).1;
+        }
+    }
+
+    pub fn get_free_ranges(&self) -> &[(vk::DeviceSize, vk::DeviceSize)]{
+        &self.free_ranges
+    }
+
+    //A single block, alignment padding counts as free
+    pub fn get_stats(&self) -> Memory_stats{
+        Memory_stats{
+            block_count: 1,
+            allocation_count: self.allocation_count,
+            reserved_bytes: self.size,
+            used_bytes: self.size - self.free_ranges.iter().map(|(_, size)| size).sum::<vk::DeviceSize>(),
+        }
+    }
+}
+
+impl std::iter::Sum for Memory_stats{
+    fn sum<I: Iterator<Item=Self>>(iter: I) -> Self{
+        iter.fold(Memory_stats::default(), |total, stats| Memory_stats{
+            block_count: total.block_count + stats.block_count,
+            allocation_count: total.allocation_count + stats.allocation_count,
+            reserved_bytes: total.reserved_bytes + stats.reserved_bytes,
+            used_bytes: total.used_bytes + stats.used_bytes,
+        })
+    }
+}
@@ -1,4 +1,20 @@
 //Made by Han_feng
 
 pub mod vulkan_application;
-pub mod utils;
\ No newline at end of file
+pub mod buffers;
+pub mod descriptors;
+pub mod draw_list;
+#[cfg(all(debug_assertions, feature = "hot_reload"))]
+pub mod hot_reload;
+pub mod index_buffers;
+pub mod memory_allocator;
+pub mod pipeline_cache;
+pub mod pipeline_manifest;
+pub mod pipelines;
+pub mod shader_reflection;
+pub mod shader_store;
+pub mod shaders;
+pub mod textures;
+pub mod uniform_buffers;
+pub mod utils;
+pub mod vertex_buffers;
@@ -0,0 +1,245 @@
+//Made by Han_feng
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use ash::vk;
+use serde::{Deserialize, Serialize};
+use crate::libs::shader_store::Shader_hash;
+use crate::libs::utils::Vulkan_result;
+use crate::save_log;
+
+cfg_if::cfg_if! {
+    if #[cfg(debug_assertions)] {
+        use crate::libs::utils::Log_level;
+        use log::Level;
+    }
+}
+
+//Consts
+pub const PIPELINE_CACHE_HEADER_SIZE: usize = 16 + vk::UUID_SIZE;
+
+//Appended to the cache path for the file recording which shaders the cache was built from
+const SHADER_RECORD_EXTENSION: &str = "shaders.json";
+
+//Enums
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pipeline_cache_error{
+    Truncated{size: usize},
+    Invalid_header_size{header_size: u32, size: usize},
+    Unsupported_header_version(u32),
+    Vendor_mismatch{expected: u32, found: u32},
+    Device_mismatch{expected: u32, found: u32},
+    Uuid_mismatch{expected: [u8; vk::UUID_SIZE], found: [u8; vk::UUID_SIZE]},
+}
+
+//Structs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pipeline_cache_header{
+    pub header_size: u32,
+    pub header_version: u32,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub pipeline_cache_uuid: [u8; vk::UUID_SIZE],
+}
+
+pub(crate) struct Pipeline_cache{
+    pub(crate) cache: vk::PipelineCache,
+    path: Option<PathBuf>,
+    //Vertex and fragment shader hashes of every pipeline in the loaded data
+    shader_record: Shader_record,
+}
+
+//Pipeline name to the hashes of its vertex and fragment shader
+pub(crate) type Shader_hashes = BTreeMap<String, [Shader_hash; 2]>;
+
+#[derive(Default, Serialize, Deserialize)]
+struct Shader_record{
+    pipelines: Shader_hashes,
+}
+
+//Functions
+pub fn validate_pipeline_cache(data: &[u8], vendor_id: u32, device_id: u32, pipeline_cache_uuid: &[u8; vk::UUID_SIZE]) -> Result<Pipeline_cache_header, Pipeline_cache_error>{
+    let header = Pipeline_cache_header::parse(data)?;
+    header.check(vendor_id, device_id, pipeline_cache_uuid)?;
+
+    Ok(header)
+}
+
+//Impls
+impl Pipeline_cache_header{
+    pub fn parse(data: &[u8]) -> Result<Self, Pipeline_cache_error>{
+        if data.len() < PIPELINE_CACHE_HEADER_SIZE{
+            return Err(Pipeline_cache_error::Truncated{size: data.len()})
+        }
+
+        //The header is a sequence of uint32_t written in the byte order of the host
+        let read_u32 = |offset: usize| u32::from_ne_bytes(data[offset..offset+4].try_into().unwrap());
+
+        let header = Pipeline_cache_header{
+            header_size: read_u32(0),
+            header_version: read_u32(4),
+            vendor_id: read_u32(8),
+            device_id: read_u32(12),
+            pipeline_cache_uuid: data[16..PIPELINE_CACHE_HEADER_SIZE].try_into().unwrap(),
+        };
+
+        if (header.header_size as usize) < PIPELINE_CACHE_HEADER_SIZE || header.header_size as usize > data.len(){
+            return Err(Pipeline_cache_error::Invalid_header_size{header_size: header.header_size, size: data.len()})
+        }
+
+        if header.header_version != vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32{
+            return Err(Pipeline_cache_error::Unsupported_header_version(header.header_version))
+        }
+
+        Ok(header)
+    }
+
+    pub fn check(&self, vendor_id: u32, device_id: u32, pipeline_cache_uuid: &[u8; vk::UUID_SIZE]) -> Result<(), Pipeline_cache_error>{
+        if self.vendor_id != vendor_id{
+            Err(Pipeline_cache_error::Vendor_mismatch{expected: vendor_id, found: self.vendor_id})
+        }
+        else if self.device_id != device_id{
+            Err(Pipeline_cache_error::Device_mismatch{expected: device_id, found: self.device_id})
+        }
+        else if &self.pipeline_cache_uuid != pipeline_cache_uuid{
+            Err(Pipeline_cache_error::Uuid_mismatch{expected: *pipeline_cache_uuid, found: self.pipeline_cache_uuid})
+        }
+        else{
+            Ok(())
+        }
+    }
+}
+
+impl Display for Pipeline_cache_error{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Truncated{size} => write!(f, "cache data is truncated ({} bytes, header needs {})", size, PIPELINE_CACHE_HEADER_SIZE),
+            Self::Invalid_header_size{header_size, size} => write!(f, "invalid header size {} for {} bytes of cache data", header_size, size),
+            Self::Unsupported_header_version(version) => write!(f, "unsupported header version {}", version),
+            Self::Vendor_mismatch{expected, found} => write!(f, "vendor id mismatch (expected {:#x}, found {:#x})", expected, found),
+            Self::Device_mismatch{expected, found} => write!(f, "device id mismatch (expected {:#x}, found {:#x})", expected, found),
+            Self::Uuid_mismatch{expected, found} => write!(f, "pipeline cache uuid mismatch (expected {:02x?}, found {:02x?})", expected, found),
+        }
+    }
+}
+
+impl std::error::Error for Pipeline_cache_error{}
+
+#[allow(unused_variables)]
+impl Pipeline_cache{
+    pub(crate) fn new(device: &ash::Device, properties: &vk::PhysicalDeviceProperties, path: Option<impl AsRef<Path>>) -> Vulkan_result<Self>{
+        let path = path.map(|path| path.as_ref().to_path_buf());
+
+        let initial_data = path.as_ref().and_then(|path| match fs::read(path) {
+            Ok(data) => {
+                save_log!(Log_level::General, Level::Info, "Loaded {} bytes of pipeline cache from {}", data.len(), path.display());
+                Some(data)
+            },
+            Err(error) => {
+                save_log!(Log_level::General, Level::Info, "No pipeline cache loaded from {}: {}", path.display(), error);
+                None
+            }
+        }).filter(|data| match validate_pipeline_cache(data, properties.vendor_id, properties.device_id, &properties.pipeline_cache_uuid) {
+            Ok(_) => true,
+            Err(error) => {
+                save_log!(Log_level::General, Level::Warn, "Discard pipeline cache: {}", error);
+                false
+            }
+        }).unwrap_or_default();
+
+        //A record only means something next to the data it was written with
+        let shader_record = path.as_ref().filter(|_| !initial_data.is_empty()).map(|path| Self::load_shader_record(path)).unwrap_or_default();
+
+        let create_info = vk::PipelineCacheCreateInfo::default()
+            .initial_data(&initial_data);
+
+        let cache = match unsafe { device.create_pipeline_cache(&create_info, None) } {
+            Ok(cache) => cache,
+            Err(error) if !initial_data.is_empty() => {
+                save_log!(Log_level::General, Level::Warn, "Pipeline cache data was rejected ({:?}), fall back to an empty cache", error);
+                unsafe { device.create_pipeline_cache(&vk::PipelineCacheCreateInfo::default(), None)? }
+            },
+            Err(error) => Err(error)?,
+        };
+
+        save_log!(Log_level::General, Level::Info, "Successfully created pipeline cache");
+
+        Ok(Pipeline_cache{
+            cache, path, shader_record
+        })
+    }
+
+    //Driver caches cannot drop single entries, so when a pipeline's shaders changed since the data was loaded the whole file is dropped
+    //The next run starts cold and saves a cache that only holds current pipelines
+    pub(crate) fn save(&self, device: &ash::Device, shader_hashes: &Shader_hashes) -> Vulkan_result<()>{
+        let Some(path) = &self.path else {
+            return Ok(())
+        };
+
+        if let Some(cache_dir) = path.parent() && !cache_dir.as_os_str().is_empty() && !cache_dir.exists(){
+            fs::create_dir_all(cache_dir)?;
+        }
+
+        let changed = shader_hashes.iter()
+            .filter(|(name, hashes)| self.shader_record.pipelines.get(*name).is_some_and(|recorded| recorded != *hashes))
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+
+        if changed.is_empty() {
+            let data = unsafe { device.get_pipeline_cache_data(self.cache)? };
+            Self::write(path, &data)?;
+
+            save_log!(Log_level::General, Level::Info, "Saved {} bytes of pipeline cache to {}", data.len(), path.display());
+        }
+        else if path.exists() {
+            fs::remove_file(path)?;
+
+            save_log!(Log_level::General, Level::Warn, "Shaders of {:?} changed, discarded stale pipeline cache {}", changed, path.display());
+        }
+
+        let record = Shader_record{
+            pipelines: shader_hashes.clone(),
+        };
+        Self::write(&Self::get_shader_record_path(path), serde_json::to_string_pretty(&record).unwrap().as_bytes())?;
+
+        Ok(())
+    }
+
+    //Write beside the target first so that a crash never leaves a half written file behind
+    fn write(path: &Path, data: &[u8]) -> Vulkan_result<()>{
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, data)?;
+        fs::rename(&temp_path, path)?;
+
+        Ok(())
+    }
+
+    fn load_shader_record(path: &Path) -> Shader_record{
+        let record_path = Self::get_shader_record_path(path);
+
+        match fs::read_to_string(&record_path).map(|text| serde_json::from_str(&text)) {
+            Ok(Ok(record)) => record,
+            Ok(Err(error)) => {
+                save_log!(Log_level::General, Level::Warn, "Ignore shader record {}: {}", record_path.display(), error);
+                Shader_record::default()
+            },
+            Err(_) => Shader_record::default(),
+        }
+    }
+
+    fn get_shader_record_path(path: &Path) -> PathBuf{
+        let mut record_path = path.as_os_str().to_owned();
+        record_path.push(".");
+        record_path.push(SHADER_RECORD_EXTENSION);
+
+        PathBuf::from(record_path)
+    }
+
+    pub(crate) fn destroy(&self, device: &ash::Device){
+        unsafe {
+            device.destroy_pipeline_cache(self.cache, None);
+        }
+    }
+}
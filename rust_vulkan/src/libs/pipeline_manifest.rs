@@ -0,0 +1,188 @@
+//Made by Han_feng
+
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use ash::vk;
+use serde::Deserialize;
+use crate::libs::vertex_buffers::Vertex_layout;
+
+//Enums
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Topology{
+    Point_list,
+    Line_list,
+    Line_strip,
+    #[default]
+    Triangle_list,
+    Triangle_strip,
+    Triangle_fan,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Blend_mode{
+    Opaque,
+    #[default]
+    Alpha,
+    Premultiplied,
+    Additive,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Cull_mode{
+    None,
+    Front,
+    #[default]
+    Back,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Render_format{
+    R8g8b8a8_srgb,
+    B8g8r8a8_srgb,
+    R8g8b8a8_unorm,
+    B8g8r8a8_unorm,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Manifest_error{
+    Io(String),
+    Parse(String),
+    Duplicate_name(String),
+}
+
+//Structs
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pipeline_manifest{
+    pub pipelines: Vec<Pipeline_entry>,
+}
+
+//Without a format the pipeline targets the render pass of the attached surface
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pipeline_entry{
+    pub name: String,
+    pub vertex_shader: String,
+    pub fragment_shader: String,
+    #[serde(default)]
+    pub topology: Topology,
+    #[serde(default)]
+    pub blend: Blend_mode,
+    #[serde(default)]
+    pub cull: Cull_mode,
+    #[serde(default)]
+    pub format: Option<Render_format>,
+    //Selects the shader variants, each shader only uses the features it declares
+    #[serde(default)]
+    pub features: Vec<String>,
+    //Replaces the tightly packed layout reflected from the vertex shader inputs
+    #[serde(default)]
+    pub vertex_layout: Option<Vertex_layout>,
+    //Read from a second buffer once per instance, needs a vertex_layout as well
+    #[serde(default)]
+    pub instance_layout: Option<Vertex_layout>,
+}
+
+//Impls
+impl Pipeline_manifest{
+    pub fn parse(source: &str) -> Result<Self, Manifest_error>{
+        let manifest = serde_json::from_str::<Pipeline_manifest>(source).map_err(|error| Manifest_error::Parse(error.to_string()))?;
+
+        let mut names = HashSet::new();
+        if let Some(entry) = manifest.pipelines.iter().find(|entry| !names.insert(entry.name.as_str())){
+            return Err(Manifest_error::Duplicate_name(entry.name.clone()))
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Manifest_error>{
+        let source = fs::read_to_string(path.as_ref()).map_err(|error| Manifest_error::Io(format!("{}: {}", path.as_ref().display(), error)))?;
+
+        Self::parse(&source)
+    }
+}
+
+impl From<Topology> for vk::PrimitiveTopology{
+    fn from(topology: Topology) -> Self {
+        match topology {
+            Topology::Point_list => vk::PrimitiveTopology::POINT_LIST,
+            Topology::Line_list => vk::PrimitiveTopology::LINE_LIST,
+            Topology::Line_strip => vk::PrimitiveTopology::LINE_STRIP,
+            Topology::Triangle_list => vk::PrimitiveTopology::TRIANGLE_LIST,
+            Topology::Triangle_strip => vk::PrimitiveTopology::TRIANGLE_STRIP,
+            Topology::Triangle_fan => vk::PrimitiveTopology::TRIANGLE_FAN,
+        }
+    }
+}
+
+impl From<Cull_mode> for vk::CullModeFlags{
+    fn from(cull_mode: Cull_mode) -> Self {
+        match cull_mode {
+            Cull_mode::None => vk::CullModeFlags::NONE,
+            Cull_mode::Front => vk::CullModeFlags::FRONT,
+            Cull_mode::Back => vk::CullModeFlags::BACK,
+        }
+    }
+}
+
+impl From<Render_format> for vk::Format{
+    fn from(format: Render_format) -> Self {
+        match format {
+            Render_format::R8g8b8a8_srgb => vk::Format::R8G8B8A8_SRGB,
+            Render_format::B8g8r8a8_srgb => vk::Format::B8G8R8A8_SRGB,
+            Render_format::R8g8b8a8_unorm => vk::Format::R8G8B8A8_UNORM,
+            Render_format::B8g8r8a8_unorm => vk::Format::B8G8R8A8_UNORM,
+        }
+    }
+}
+
+impl Blend_mode{
+    pub(crate) fn get_attachment_state(&self) -> vk::PipelineColorBlendAttachmentState{
+        let state = vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .alpha_blend_op(vk::BlendOp::ADD);
+
+        match self {
+            Blend_mode::Opaque => state
+                .blend_enable(false),
+            Blend_mode::Alpha => state
+                .blend_enable(true)
+                .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                .dst_alpha_blend_factor(vk::BlendFactor::ZERO),
+            Blend_mode::Premultiplied => state
+                .blend_enable(true)
+                .src_color_blend_factor(vk::BlendFactor::ONE)
+                .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA),
+            Blend_mode::Additive => state
+                .blend_enable(true)
+                .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                .dst_color_blend_factor(vk::BlendFactor::ONE)
+                .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                .dst_alpha_blend_factor(vk::BlendFactor::ONE),
+        }
+    }
+}
+
+impl Display for Manifest_error{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(message) => write!(f, "failed to read pipeline manifest ({})", message),
+            Self::Parse(message) => write!(f, "failed to parse pipeline manifest ({})", message),
+            Self::Duplicate_name(name) => write!(f, "pipeline {} is declared more than once", name),
+        }
+    }
+}
+
+impl std::error::Error for Manifest_error{}
@@ -0,0 +1,817 @@
+//Made by Han_feng
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use ash::vk;
+use crate::libs::draw_list::MAX_DRAW_DESCRIPTOR_SETS;
+use crate::libs::pipeline_cache::{Pipeline_cache, Shader_hashes};
+use crate::libs::pipeline_manifest::{Blend_mode, Pipeline_entry, Pipeline_manifest};
+use crate::libs::shader_reflection::{Descriptor_binding, Pipeline_reflection, Push_constant_range, Shader_reflection};
+use crate::libs::shader_store::Shader_hash;
+use crate::libs::shaders::{get_embedded_shader, Shader_code, Shader_library, Shader_stage, DEFAULT_FRAGMENT_SHADER, DEFAULT_VERTEX_SHADER};
+use crate::libs::utils::{Status_code, Vulkan_result};
+use crate::libs::vertex_buffers::{check_vertex_input, Colored_vertex, Vertex, Vertex_layout};
+use crate::save_log;
+
+cfg_if::cfg_if! {
+    if #[cfg(debug_assertions)] {
+        use crate::libs::utils::Log_level;
+        use log::Level;
+    }
+}
+
+#[cfg(all(debug_assertions, feature = "hot_reload"))]
+use crate::libs::descriptors::Descriptor_sets;
+#[cfg(all(debug_assertions, feature = "hot_reload"))]
+use crate::libs::hot_reload::Shader_source;
+
+//Consts
+const MAX_COMPILE_THREADS: usize = 4;
+
+pub const DEFAULT_PIPELINE_NAME: &str = "default";
+
+//Enums
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub enum Pipeline_status{
+    Pending = 0,
+    Ready = 1,
+    Failed = 2,
+}
+
+//Structs
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Pipeline_handle(pub u64);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pipeline_report{
+    pub name: String,
+    pub handle: Pipeline_handle,
+    pub status: Pipeline_status,
+}
+
+#[derive(Clone)]
+pub(crate) struct Pipeline_description{
+    vertex_shader: Shader_code,
+    fragment_shader: Shader_code,
+    topology: vk::PrimitiveTopology,
+    blend: Blend_mode,
+    cull_mode: vk::CullModeFlags,
+    format: Option<vk::Format>,
+    vertex_layout: Option<Vertex_layout>,
+    instance_layout: Option<Vertex_layout>,
+
+    //Where the shaders came from, so edited sources can be recompiled the same way
+    #[cfg(all(debug_assertions, feature = "hot_reload"))]
+    shader_names: [String; 2],
+    #[cfg(all(debug_assertions, feature = "hot_reload"))]
+    features: Vec<String>,
+}
+
+pub(crate) struct Graphics_pipelines {
+    names: Vec<String>,
+    //None when the description could not even be resolved, such slots stay failed
+    descriptions: Vec<Option<Pipeline_description>>,
+    interfaces: Vec<Option<Pipeline_interface>>,
+    slots: Arc<Pipeline_slots>,
+    compiler: Pipeline_compiler,
+
+    //Render passes
+    render_pass: vk::RenderPass,
+    format_render_passes: HashMap<vk::Format, vk::RenderPass>,
+
+    //Limits
+    max_push_constants_size: u32,
+}
+
+struct Pipeline_slots{
+    slots: Mutex<Vec<Pipeline_slot>>,
+    changed: Condvar,
+}
+
+#[derive(Copy, Clone)]
+struct Pipeline_slot{
+    status: Pipeline_status,
+    pipeline: vk::Pipeline,
+    generation: u64,
+}
+
+struct Pipeline_compiler{
+    sender: Option<mpsc::Sender<Compile_job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+//Everything derived from reflecting the shaders of a pipeline
+#[derive(Clone)]
+pub(crate) struct Pipeline_interface{
+    //Vertex then fragment, HLSL shaders keep the name of their entry function
+    entry_points: Vec<CString>,
+    pub(crate) layout: vk::PipelineLayout,
+    //One per set index up to the highest one used, unused ones are empty
+    pub(crate) set_layouts: Vec<vk::DescriptorSetLayout>,
+    descriptor_bindings: Vec<Descriptor_binding>,
+    //Shared by every stage
+    pub(crate) push_constants: Option<Push_constant_range>,
+    vertex_bindings: Vec<vk::VertexInputBindingDescription>,
+    vertex_attributes: Vec<vk::VertexInputAttributeDescription>,
+}
+
+struct Compile_job{
+    handle: Pipeline_handle,
+    generation: u64,
+    description: Pipeline_description,
+    interface: Pipeline_interface,
+    render_pass: vk::RenderPass,
+}
+
+//Functions
+pub(crate) fn create_render_pass(device: &ash::Device, image_format: vk::Format) -> Vulkan_result<vk::RenderPass> {
+    let attachment_descriptions = [
+        vk::AttachmentDescription::default()
+            .format(image_format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+    ];
+
+    let attachment_refs = [
+        vk::AttachmentReference::default()
+            .attachment(0)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+    ];
+
+    let subpasses = [
+        vk::SubpassDescription::default()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&attachment_refs)
+    ];
+
+    let dependencies = [
+        vk::SubpassDependency::default()
+            .src_subpass(vk::SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(vk::AccessFlags::empty())
+            .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+    ];
+
+    let create_info = vk::RenderPassCreateInfo::default()
+        .attachments(&attachment_descriptions)
+        .subpasses(&subpasses)
+        .dependencies(&dependencies);
+
+    let render_pass = unsafe { device.create_render_pass(&create_info, None)? };
+
+    save_log!(Log_level::General, Level::Info, "Successfully created render pass");
+
+    Ok(render_pass)
+}
+
+//Impls
+impl Pipeline_handle{
+    pub const DEFAULT: Self = Pipeline_handle(0);
+}
+
+impl Default for Pipeline_description{
+    fn default() -> Self {
+        Pipeline_description{
+            vertex_shader: get_embedded_shader(DEFAULT_VERTEX_SHADER).unwrap(),
+            fragment_shader: get_embedded_shader(DEFAULT_FRAGMENT_SHADER).unwrap(),
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            blend: Blend_mode::Alpha,
+            cull_mode: vk::CullModeFlags::BACK,
+            format: None,
+            vertex_layout: Some(Colored_vertex::get_layout()),
+            instance_layout: None,
+
+            #[cfg(all(debug_assertions, feature = "hot_reload"))]
+            shader_names: [DEFAULT_VERTEX_SHADER.to_string(), DEFAULT_FRAGMENT_SHADER.to_string()],
+            #[cfg(all(debug_assertions, feature = "hot_reload"))]
+            features: vec![],
+        }
+    }
+}
+
+impl Pipeline_description{
+    fn from_entry(entry: &Pipeline_entry, shader_library: &Shader_library) -> Result<Self, String>{
+        let find_shader = |name: &str, stage| shader_library.get_variant(name, stage, &entry.features).ok_or_else(|| format!("unknown {:?} shader {}", stage, name));
+
+        Ok(Pipeline_description{
+            vertex_shader: find_shader(&entry.vertex_shader, Shader_stage::Vertex)?,
+            fragment_shader: find_shader(&entry.fragment_shader, Shader_stage::Fragment)?,
+            topology: entry.topology.into(),
+            blend: entry.blend,
+            cull_mode: entry.cull.into(),
+            format: entry.format.map(|format| format.into()),
+            vertex_layout: entry.vertex_layout.clone(),
+            instance_layout: entry.instance_layout.clone(),
+
+            #[cfg(all(debug_assertions, feature = "hot_reload"))]
+            shader_names: [entry.vertex_shader.clone(), entry.fragment_shader.clone()],
+            #[cfg(all(debug_assertions, feature = "hot_reload"))]
+            features: entry.features.clone(),
+        })
+    }
+
+    //None when neither shader is among the changed sources
+    #[cfg(all(debug_assertions, feature = "hot_reload"))]
+    fn reload(&self, sources: &[Shader_source], compile: &impl Fn(&Shader_source, &[String]) -> Result<Shader_code, String>) -> Result<Option<Self>, String>{
+        let find_source = |name: &String, stage| sources.iter().find(|source| source.name == *name && source.stage == stage);
+        let (vertex_source, fragment_source) = (find_source(&self.shader_names[0], Shader_stage::Vertex), find_source(&self.shader_names[1], Shader_stage::Fragment));
+        if vertex_source.is_none() && fragment_source.is_none() {
+            return Ok(None)
+        }
+
+        let mut description = self.clone();
+        if let Some(source) = vertex_source {
+            description.vertex_shader = compile(source, &self.features)?;
+        }
+        if let Some(source) = fragment_source {
+            description.fragment_shader = compile(source, &self.features)?;
+        }
+
+        Ok(Some(description))
+    }
+
+    fn get_shader_hashes(&self) -> [Shader_hash; 2]{
+        [Shader_hash::new(&self.vertex_shader), Shader_hash::new(&self.fragment_shader)]
+    }
+
+    //Each module is reflected at the entry point of its stage, so a module in the wrong slot fails here
+    fn reflect(&self, max_push_constants_size: u32) -> Result<Pipeline_reflection, String>{
+        let reflection = Pipeline_reflection::new(&[
+            Shader_reflection::new_for_stage(&self.vertex_shader, vk::ShaderStageFlags::VERTEX).map_err(|error| error.to_string())?,
+            Shader_reflection::new_for_stage(&self.fragment_shader, vk::ShaderStageFlags::FRAGMENT).map_err(|error| error.to_string())?,
+        ]).map_err(|error| error.to_string())?;
+
+        reflection.check_push_constants(max_push_constants_size).map_err(|error| error.to_string())?;
+
+        //A draw command has no room for more sets
+        if reflection.get_set_count() as usize > MAX_DRAW_DESCRIPTOR_SETS {
+            return Err(format!("shaders use {} descriptor sets, a draw binds at most {}", reflection.get_set_count(), MAX_DRAW_DESCRIPTOR_SETS))
+        }
+
+        match (&self.vertex_layout, &self.instance_layout) {
+            (Some(vertex_layout), instance_layout) => {
+                let layouts = [Some(vertex_layout), instance_layout.as_ref()].into_iter().flatten().collect::<Vec<_>>();
+                check_vertex_input(&layouts, &reflection.vertex_inputs).map_err(|error| error.to_string())?;
+            },
+            (None, Some(_)) => return Err("an instance layout needs a vertex layout".to_string()),
+            (None, None) => (),
+        }
+
+        Ok(reflection)
+    }
+}
+
+impl Pipeline_interface{
+    //The vertex layout is bound at 0 and the instance layout at 1
+    fn new(device: &ash::Device, reflection: &Pipeline_reflection, description: &Pipeline_description) -> Vulkan_result<Self>{
+        let (vertex_bindings, vertex_attributes) = match &description.vertex_layout {
+            Some(layout) => {
+                let mut bindings = vec![layout.get_binding_description(0, vk::VertexInputRate::VERTEX)];
+                let mut attributes = layout.get_attribute_descriptions(0);
+                if let Some(instance_layout) = &description.instance_layout {
+                    bindings.push(instance_layout.get_binding_description(1, vk::VertexInputRate::INSTANCE));
+                    attributes.extend(instance_layout.get_attribute_descriptions(1));
+                }
+
+                (bindings, attributes)
+            },
+            None => reflection.get_vertex_input(),
+        };
+
+        let mut interface = Pipeline_interface{
+            entry_points: reflection.entry_points.iter().map(|entry_point| CString::new(entry_point.as_str()).unwrap_or_default()).collect(),
+            layout: vk::PipelineLayout::null(),
+            set_layouts: vec![],
+            descriptor_bindings: reflection.descriptor_bindings.clone(),
+            push_constants: reflection.push_constants,
+            vertex_bindings, vertex_attributes,
+        };
+
+        for set in 0..reflection.get_set_count() {
+            let bindings = reflection.descriptor_bindings.iter().filter(|binding| binding.set == set).map(|binding| {
+                vk::DescriptorSetLayoutBinding::default()
+                    .binding(binding.binding)
+                    .descriptor_type(binding.descriptor_type)
+                    .descriptor_count(binding.count)
+                    .stage_flags(binding.stages)
+            }).collect::<Vec<_>>();
+
+            let create_info = vk::DescriptorSetLayoutCreateInfo::default()
+                .bindings(&bindings);
+
+            match unsafe { device.create_descriptor_set_layout(&create_info, None) } {
+                Ok(set_layout) => interface.set_layouts.push(set_layout),
+                Err(error) => {
+                    interface.destroy(device);
+                    Err(error)?
+                }
+            }
+        }
+
+        let push_constant_ranges = reflection.push_constants.iter().map(|range| {
+            vk::PushConstantRange::default()
+                .stage_flags(range.stages)
+                .offset(range.offset)
+                .size(range.size)
+        }).collect::<Vec<_>>();
+
+        let layout_info = vk::PipelineLayoutCreateInfo::default()
+            .set_layouts(&interface.set_layouts)
+            .push_constant_ranges(&push_constant_ranges);
+
+        match unsafe { device.create_pipeline_layout(&layout_info, None) } {
+            Ok(layout) => interface.layout = layout,
+            Err(error) => {
+                interface.destroy(device);
+                Err(error)?
+            }
+        }
+
+        Ok(interface)
+    }
+
+    //Sorted by binding
+    pub(crate) fn get_set_bindings(&self, set: u32) -> Vec<Descriptor_binding>{
+        self.descriptor_bindings.iter().filter(|binding| binding.set == set).copied().collect()
+    }
+
+    fn destroy(&self, device: &ash::Device){
+        unsafe {
+            device.destroy_pipeline_layout(self.layout, None);
+            self.set_layouts.iter().for_each(|&set_layout| device.destroy_descriptor_set_layout(set_layout, None));
+        }
+    }
+}
+
+impl Graphics_pipelines {
+    pub(crate) fn new(device: &ash::Device, render_pass: vk::RenderPass, pipeline_cache: &Pipeline_cache, max_push_constants_size: u32) -> Vulkan_result<Self>{
+        let slots = Arc::new(Pipeline_slots{
+            slots: Mutex::new(vec![]),
+            changed: Condvar::new(),
+        });
+        let compiler = Pipeline_compiler::new(device, pipeline_cache, &slots)?;
+
+        let mut result = Graphics_pipelines{
+            slots, compiler, render_pass, max_push_constants_size,
+            names: vec![],
+            descriptions: vec![],
+            interfaces: vec![],
+            format_render_passes: HashMap::new(),
+        };
+
+        result.add(device, DEFAULT_PIPELINE_NAME, Pipeline_description::default())?;
+
+        Ok(result)
+    }
+
+    //Shaders that do not fit together leave a failed slot behind instead of an error
+    #[allow(unused_variables)]
+    pub(crate) fn add(&mut self, device: &ash::Device, name: &str, description: Pipeline_description) -> Vulkan_result<Pipeline_handle>{
+        let reflection = match description.reflect(self.max_push_constants_size) {
+            Ok(reflection) => reflection,
+            Err(error) => {
+                save_log!(Log_level::General, Level::Error, "Failed to reflect graphics pipeline {}: {}", name, error);
+                return Ok(self.push_slot(name, None, None, Pipeline_status::Failed))
+            }
+        };
+
+        let render_pass = self.get_render_pass(device, description.format)?;
+        let interface = Pipeline_interface::new(device, &reflection, &description)?;
+        let handle = self.push_slot(name, Some(description.clone()), Some(interface.clone()), Pipeline_status::Pending);
+
+        self.compiler.submit(Compile_job{
+            handle, description, interface, render_pass,
+            generation: 0,
+        })?;
+
+        Ok(handle)
+    }
+
+    #[allow(unused_variables)]
+    pub(crate) fn load_manifest(&mut self, device: &ash::Device, manifest: &Pipeline_manifest, shader_library: &Shader_library) -> Vulkan_result<Vec<Pipeline_handle>>{
+        let handles = manifest.pipelines.iter().map(|entry| {
+            match Pipeline_description::from_entry(entry, shader_library) {
+                Ok(description) => self.add(device, &entry.name, description),
+                Err(reason) => {
+                    save_log!(Log_level::General, Level::Error, "Failed to create graphics pipeline {}: {}", entry.name, reason);
+                    Ok(self.push_slot(&entry.name, None, None, Pipeline_status::Failed))
+                }
+            }
+        }).collect::<Vulkan_result<Vec<_>>>()?;
+
+        save_log!(Log_level::General, Level::Info, "Submitted {} graphics pipelines from manifest", handles.len());
+
+        Ok(handles)
+    }
+
+    //Later entries shadow earlier ones with the same name
+    pub(crate) fn find(&self, name: &str) -> Option<Pipeline_handle>{
+        self.names.iter().rposition(|n| n == name).map(|index| Pipeline_handle(index as u64))
+    }
+
+    pub(crate) fn get(&self, handle: Pipeline_handle) -> Option<vk::Pipeline>{
+        self.slots.slots.lock().unwrap().get(handle.0 as usize)
+            .filter(|slot| slot.status == Pipeline_status::Ready)
+            .map(|slot| slot.pipeline)
+    }
+
+    pub(crate) fn get_status(&self, handle: Pipeline_handle) -> Option<Pipeline_status>{
+        self.slots.slots.lock().unwrap().get(handle.0 as usize).map(|slot| slot.status)
+    }
+
+    //Vertex then instance layout, a pipeline reading the layout reflected from its vertex shader has neither
+    pub(crate) fn get_vertex_layouts(&self, handle: Pipeline_handle) -> Option<(Option<&Vertex_layout>, Option<&Vertex_layout>)>{
+        let description = self.descriptions.get(handle.0 as usize)?.as_ref()?;
+
+        Some((description.vertex_layout.as_ref(), description.instance_layout.as_ref()))
+    }
+
+    //None when the pipeline description could not be resolved
+    pub(crate) fn get_interface(&self, handle: Pipeline_handle) -> Option<&Pipeline_interface>{
+        self.interfaces.get(handle.0 as usize)?.as_ref()
+    }
+
+    pub(crate) fn wait(&self, handle: Pipeline_handle) -> Option<Pipeline_status>{
+        let slots = self.slots.slots.lock().unwrap();
+        let slots = self.slots.changed.wait_while(slots, |slots| {
+            slots.get(handle.0 as usize).is_some_and(|slot| slot.status == Pipeline_status::Pending)
+        }).unwrap();
+
+        slots.get(handle.0 as usize).map(|slot| slot.status)
+    }
+
+    pub(crate) fn wait_all(&self) -> Vec<Pipeline_report>{
+        let slots = self.slots.slots.lock().unwrap();
+        let slots = self.slots.changed.wait_while(slots, |slots| {
+            slots.iter().any(|slot| slot.status == Pipeline_status::Pending)
+        }).unwrap();
+
+        slots.iter().zip(self.names.iter()).enumerate().map(|(index, (slot, name))| Pipeline_report{
+            name: name.clone(),
+            handle: Pipeline_handle(index as u64),
+            status: slot.status,
+        }).collect()
+    }
+
+    //Later entries shadow earlier ones with the same name, as in find
+    pub(crate) fn get_shader_hashes(&self) -> Shader_hashes{
+        self.names.iter().zip(self.descriptions.iter())
+            .filter_map(|(name, description)| Some((name.clone(), description.as_ref()?.get_shader_hashes())))
+            .collect()
+    }
+
+    //Only pipelines that follow the surface format are rebuilt against the new render pass
+    //The caller must make sure none of them is still in use by the device
+    pub(crate) fn rebuild(&mut self, device: &ash::Device, render_pass: vk::RenderPass) -> Vulkan_result<()>{
+        self.render_pass = render_pass;
+
+        let jobs = {
+            let mut slots = self.slots.slots.lock().unwrap();
+            slots.iter_mut().zip(self.descriptions.iter().zip(self.interfaces.iter())).enumerate()
+                .filter_map(|(index, (slot, (description, interface)))| match (description, interface) {
+                    (Some(description), Some(interface)) if description.format.is_none() => Some((index, slot, description, interface)),
+                    _ => None,
+                })
+                .map(|(index, slot, description, interface)| {
+                    if slot.status == Pipeline_status::Ready {
+                        unsafe { device.destroy_pipeline(slot.pipeline, None) };
+                    }
+
+                    slot.status = Pipeline_status::Pending;
+                    slot.pipeline = vk::Pipeline::null();
+                    slot.generation += 1;
+
+                    Compile_job{
+                        handle: Pipeline_handle(index as u64),
+                        generation: slot.generation,
+                        description: description.clone(),
+                        interface: interface.clone(),
+                        render_pass,
+                    }
+                }).collect::<Vec<_>>()
+        };
+
+        save_log!(Log_level::General, Level::Info, "Resubmitted {} graphics pipelines", jobs.len());
+
+        jobs.into_iter().try_for_each(|job| self.compiler.submit(job))
+    }
+
+    //Pipelines using a changed shader are rebuilt in place, one that fails keeps its previous pipeline
+    //Descriptor sets created from a replaced pipeline's layouts are invalidated along with them
+    #[cfg(all(debug_assertions, feature = "hot_reload"))]
+    pub(crate) fn reload_shaders(&mut self, device: &ash::Device, pipeline_cache: &Pipeline_cache, descriptor_sets: &mut Descriptor_sets, sources: &[Shader_source], compile: impl Fn(&Shader_source, &[String]) -> Result<Shader_code, String>) -> Vulkan_result<()>{
+        //Queued jobs may still use the interfaces about to be replaced
+        self.wait_all();
+
+        let mut reloaded = vec![];
+        for index in 0..self.descriptions.len() {
+            let Some(description) = &self.descriptions[index] else {
+                continue
+            };
+
+            let description = match description.reload(sources, &compile) {
+                Ok(Some(description)) => description,
+                Ok(None) => continue,
+                Err(error) => {
+                    save_log!(Log_level::General, Level::Error, "Failed to reload graphics pipeline {}: {}", self.names[index], error);
+                    continue
+                }
+            };
+            let reflection = match description.reflect(self.max_push_constants_size) {
+                Ok(reflection) => reflection,
+                Err(error) => {
+                    save_log!(Log_level::General, Level::Error, "Failed to reflect reloaded graphics pipeline {}: {}", self.names[index], error);
+                    continue
+                }
+            };
+
+            let render_pass = self.get_render_pass(device, description.format)?;
+            let interface = Pipeline_interface::new(device, &reflection, &description)?;
+            let job = Compile_job{
+                handle: Pipeline_handle(index as u64),
+                generation: 0,
+                description, interface, render_pass,
+            };
+
+            match Self::compile(device, pipeline_cache.cache, &job) {
+                Ok(pipeline) => reloaded.push((job, pipeline)),
+                Err(_) => {
+                    job.interface.destroy(device);
+                    save_log!(Log_level::General, Level::Error, "Failed to compile reloaded graphics pipeline {}, keeping the previous one", self.names[index]);
+                }
+            }
+        }
+
+        if reloaded.is_empty() {
+            return Ok(())
+        }
+
+        //The previous pipelines may still be in flight
+        unsafe { device.device_wait_idle()? };
+
+        let mut slots = self.slots.slots.lock().unwrap();
+        for (job, pipeline) in reloaded {
+            let index = job.handle.0 as usize;
+            let slot = &mut slots[index];
+
+            if slot.status == Pipeline_status::Ready {
+                unsafe { device.destroy_pipeline(slot.pipeline, None) };
+            }
+            slot.status = Pipeline_status::Ready;
+            slot.pipeline = pipeline;
+
+            if let Some(interface) = self.interfaces[index].replace(job.interface) {
+                descriptor_sets.invalidate_layouts(device, &interface.set_layouts);
+                interface.destroy(device);
+            }
+            self.descriptions[index] = Some(job.description);
+
+            save_log!(Log_level::General, Level::Info, "Reloaded graphics pipeline {}", self.names[index]);
+        }
+        self.slots.changed.notify_all();
+
+        Ok(())
+    }
+
+    pub(crate) fn destroy(&mut self, device: &ash::Device){
+        self.compiler.shutdown();
+
+        unsafe {
+            self.slots.slots.lock().unwrap().iter()
+                .filter(|slot| slot.status == Pipeline_status::Ready)
+                .for_each(|slot| device.destroy_pipeline(slot.pipeline, None));
+            self.interfaces.iter().flatten().for_each(|interface| interface.destroy(device));
+            self.format_render_passes.values().for_each(|&render_pass| device.destroy_render_pass(render_pass, None));
+        }
+    }
+
+    fn push_slot(&mut self, name: &str, description: Option<Pipeline_description>, interface: Option<Pipeline_interface>, status: Pipeline_status) -> Pipeline_handle{
+        let handle = Pipeline_handle(self.descriptions.len() as u64);
+
+        self.slots.slots.lock().unwrap().push(Pipeline_slot{
+            status,
+            pipeline: vk::Pipeline::null(),
+            generation: 0,
+        });
+        self.names.push(name.to_string());
+        self.descriptions.push(description);
+        self.interfaces.push(interface);
+
+        handle
+    }
+
+    //Pipelines with an explicit format are built against a compatible render pass of their own
+    fn get_render_pass(&mut self, device: &ash::Device, format: Option<vk::Format>) -> Vulkan_result<vk::RenderPass>{
+        let Some(format) = format else {
+            return Ok(self.render_pass)
+        };
+
+        if let Some(&render_pass) = self.format_render_passes.get(&format) {
+            return Ok(render_pass)
+        }
+
+        let render_pass = create_render_pass(device, format)?;
+        self.format_render_passes.insert(format, render_pass);
+
+        Ok(render_pass)
+    }
+
+    fn compile(device: &ash::Device, pipeline_cache: vk::PipelineCache, job: &Compile_job) -> Vulkan_result<vk::Pipeline>{
+        let vertex_shader = Self::get_shader(device, &job.description.vertex_shader)?;
+        let fragment_shader = match Self::get_shader(device, &job.description.fragment_shader) {
+            Ok(shader) => shader,
+            Err(code) => {
+                unsafe { device.destroy_shader_module(vertex_shader, None) };
+                return Err(code)
+            }
+        };
+
+        //Shader stages
+        let stages = [
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vertex_shader)
+                .name(&job.interface.entry_points[0]),
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(fragment_shader)
+                .name(&job.interface.entry_points[1])
+        ];
+
+        //Dynamic states
+        let dynamic_state_info = vk::PipelineDynamicStateCreateInfo::default()
+            .dynamic_states(&[
+                vk::DynamicState::VIEWPORT,
+                vk::DynamicState::SCISSOR,
+            ]);
+
+        //Vertex Input
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&job.interface.vertex_bindings)
+            .vertex_attribute_descriptions(&job.interface.vertex_attributes);
+
+        //Input assembly
+        let input_assembly_info = vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(job.description.topology)
+            .primitive_restart_enable(false);
+
+        //Viewport
+        let viewport_info = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+
+        //Rasterizer
+        let rasterizer_info = vk::PipelineRasterizationStateCreateInfo::default()
+            .depth_clamp_enable(false)
+            .rasterizer_discard_enable(false)
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .cull_mode(job.description.cull_mode)
+            .front_face(vk::FrontFace::CLOCKWISE)
+            .depth_bias_enable(false);
+
+        //Multisample
+        let multisample_info = vk::PipelineMultisampleStateCreateInfo::default()
+            .sample_shading_enable(false)
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+
+        //Depth stencil
+        let _depth_stencil_info = vk::PipelineDepthStencilStateCreateInfo::default();
+
+        //Color blend
+        let color_blend_attachments = [
+            job.description.blend.get_attachment_state()
+        ];
+
+        let color_blend_info = vk::PipelineColorBlendStateCreateInfo::default()
+            .logic_op_enable(false)
+            .attachments(&color_blend_attachments)
+            .blend_constants([0.0, 0.0, 0.0, 0.0]);
+
+        //Creation
+        let create_infos = [
+            vk::GraphicsPipelineCreateInfo::default()
+            .stages(&stages)
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly_info)
+            .viewport_state(&viewport_info)
+            .rasterization_state(&rasterizer_info)
+            .multisample_state(&multisample_info)
+            .color_blend_state(&color_blend_info)
+            .dynamic_state(&dynamic_state_info)
+            .layout(job.interface.layout)
+            .render_pass(job.render_pass)
+            .subpass(0)
+        ];
+
+        let result = unsafe { device.create_graphics_pipelines(pipeline_cache, &create_infos, None).map_err(|(_, err)| err) };
+
+        unsafe {
+            device.destroy_shader_module(vertex_shader, None);
+            device.destroy_shader_module(fragment_shader, None);
+        }
+
+        Ok(result?[0])
+    }
+
+    fn get_shader(device: &ash::Device, shader_code: &[u32]) -> Vulkan_result<vk::ShaderModule>{
+        let create_info = vk::ShaderModuleCreateInfo::default()
+            .code(shader_code);
+
+        Ok(unsafe { device.create_shader_module(&create_info, None)? })
+    }
+}
+
+impl Pipeline_slots{
+    #[allow(unused_variables)]
+    fn finish(&self, device: &ash::Device, job: &Compile_job, result: Vulkan_result<vk::Pipeline>){
+        let mut slots = self.slots.lock().unwrap();
+        let slot = &mut slots[job.handle.0 as usize];
+
+        //A rebuild happened while this job was running, so its result belongs to an outdated render pass
+        if slot.generation != job.generation {
+            if let Ok(pipeline) = result {
+                unsafe { device.destroy_pipeline(pipeline, None) };
+            }
+            return
+        }
+
+        match result {
+            Ok(pipeline) => {
+                slot.status = Pipeline_status::Ready;
+                slot.pipeline = pipeline;
+                save_log!(Log_level::General, Level::Info, "Successfully created graphics pipeline {}", job.handle.0);
+            },
+            Err(code) => {
+                slot.status = Pipeline_status::Failed;
+                save_log!(Log_level::General, Level::Error, "Failed to create graphics pipeline {}: {:?}", job.handle.0, code);
+            }
+        }
+
+        self.changed.notify_all();
+    }
+}
+
+impl Pipeline_compiler{
+    fn new(device: &ash::Device, pipeline_cache: &Pipeline_cache, slots: &Arc<Pipeline_slots>) -> Vulkan_result<Self>{
+        let (sender, receiver) = mpsc::channel::<Compile_job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let thread_count = std::thread::available_parallelism()
+            .map(|count| count.get().saturating_sub(1).clamp(1, MAX_COMPILE_THREADS))
+            .unwrap_or(1);
+
+        let workers = (0..thread_count).map(|index| {
+            let device = device.clone();
+            let cache = pipeline_cache.cache;
+            let receiver = receiver.clone();
+            let slots = slots.clone();
+
+            Ok(std::thread::Builder::new()
+                .name(format!("pipeline_compiler_{}", index))
+                .spawn(move || loop {
+                    let job = match receiver.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+
+                    let result = Graphics_pipelines::compile(&device, cache, &job);
+                    slots.finish(&device, &job, result);
+                })?)
+        }).collect::<Result<Vec<_>, Status_code>>()?;
+
+        save_log!(Log_level::General, Level::Info, "Started {} pipeline compiler threads", thread_count);
+
+        Ok(Pipeline_compiler{
+            sender: Some(sender),
+            workers,
+        })
+    }
+
+    fn submit(&self, job: Compile_job) -> Vulkan_result<()>{
+        self.sender.as_ref().and_then(|sender| sender.send(job).ok()).ok_or_else(|| {
+            save_log!(Log_level::General, Level::Error, "Pipeline compiler is not running");
+
+            Status_code::Failure
+        })
+    }
+
+    //Finishes every queued job before returning
+    fn shutdown(&mut self){
+        self.sender = None;
+        self.workers.drain(..).for_each(|worker| {
+            let _ = worker.join();
+        });
+    }
+}
@@ -0,0 +1,586 @@
+//Made by Han_feng
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use ash::vk;
+use crate::libs::shaders::SPIRV_MAGIC;
+
+//Consts
+const SPIRV_HEADER_WORDS: usize = 5;
+
+//Opcodes
+const OP_NAME: u32 = 5;
+const OP_ENTRY_POINT: u32 = 15;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+const OP_TYPE_ACCELERATION_STRUCTURE: u32 = 5341;
+
+//Decorations
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+//Storage classes
+const STORAGE_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_INPUT: u32 = 1;
+const STORAGE_UNIFORM: u32 = 2;
+const STORAGE_OUTPUT: u32 = 3;
+const STORAGE_PUSH_CONSTANT: u32 = 9;
+const STORAGE_STORAGE_BUFFER: u32 = 12;
+
+//Image dimensions and sampled operand
+const DIM_BUFFER: u32 = 5;
+const DIM_SUBPASS_DATA: u32 = 6;
+const IMAGE_STORAGE: u32 = 2;
+
+//Scalar kind, width in bits and the formats with one to four components
+const ATTRIBUTE_FORMATS: &[(Scalar_kind, u32, [vk::Format; 4])] = &[
+    (Scalar_kind::Float, 16, [vk::Format::R16_SFLOAT, vk::Format::R16G16_SFLOAT, vk::Format::R16G16B16_SFLOAT, vk::Format::R16G16B16A16_SFLOAT]),
+    (Scalar_kind::Float, 32, [vk::Format::R32_SFLOAT, vk::Format::R32G32_SFLOAT, vk::Format::R32G32B32_SFLOAT, vk::Format::R32G32B32A32_SFLOAT]),
+    (Scalar_kind::Float, 64, [vk::Format::R64_SFLOAT, vk::Format::R64G64_SFLOAT, vk::Format::R64G64B64_SFLOAT, vk::Format::R64G64B64A64_SFLOAT]),
+    (Scalar_kind::Sint, 16, [vk::Format::R16_SINT, vk::Format::R16G16_SINT, vk::Format::R16G16B16_SINT, vk::Format::R16G16B16A16_SINT]),
+    (Scalar_kind::Sint, 32, [vk::Format::R32_SINT, vk::Format::R32G32_SINT, vk::Format::R32G32B32_SINT, vk::Format::R32G32B32A32_SINT]),
+    (Scalar_kind::Sint, 64, [vk::Format::R64_SINT, vk::Format::R64G64_SINT, vk::Format::R64G64B64_SINT, vk::Format::R64G64B64A64_SINT]),
+    (Scalar_kind::Uint, 16, [vk::Format::R16_UINT, vk::Format::R16G16_UINT, vk::Format::R16G16B16_UINT, vk::Format::R16G16B16A16_UINT]),
+    (Scalar_kind::Uint, 32, [vk::Format::R32_UINT, vk::Format::R32G32_UINT, vk::Format::R32G32B32_UINT, vk::Format::R32G32B32A32_UINT]),
+    (Scalar_kind::Uint, 64, [vk::Format::R64_UINT, vk::Format::R64G64_UINT, vk::Format::R64G64B64_UINT, vk::Format::R64G64B64A64_UINT]),
+];
+
+//Enums
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reflection_error{
+    Malformed{offset: usize},
+    Missing_entry_point,
+    Missing_stage(vk::ShaderStageFlags),
+    Unsupported_descriptor{set: u32, binding: u32},
+    Descriptor_conflict{set: u32, binding: u32},
+    Interface_mismatch{location: u32, output: Option<vk::Format>, input: vk::Format},
+    Push_constants_too_large{end: u32, max: u32},
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Scalar_kind{
+    Float,
+    Sint,
+    Uint,
+}
+
+enum Spirv_type{
+    Scalar{kind: Scalar_kind, width: u32},
+    Vector{component: u32, count: u32},
+    Matrix{column: u32, count: u32},
+    Image{dim: u32, sampled: u32},
+    Sampler,
+    Sampled_image,
+    Array{element: u32, length: u32},
+    Runtime_array{element: u32},
+    Struct{members: Vec<u32>},
+    Pointer{pointee: u32},
+    Acceleration_structure,
+}
+
+//Structs
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Descriptor_binding{
+    pub set: u32,
+    pub binding: u32,
+    pub descriptor_type: vk::DescriptorType,
+    pub count: u32,
+    pub stages: vk::ShaderStageFlags,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Push_constant_range{
+    pub offset: u32,
+    pub size: u32,
+    pub stages: vk::ShaderStageFlags,
+}
+
+//A matrix or array occupies one variable per location
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interface_variable{
+    pub location: u32,
+    pub format: vk::Format,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shader_reflection{
+    pub stage: vk::ShaderStageFlags,
+    pub entry_point: String,
+    pub descriptor_bindings: Vec<Descriptor_binding>,
+    pub push_constants: Option<Push_constant_range>,
+    pub inputs: Vec<Interface_variable>,
+    pub outputs: Vec<Interface_variable>,
+}
+
+//The merged view of every stage of a pipeline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pipeline_reflection{
+    //One per stage, in pipeline order
+    pub entry_points: Vec<String>,
+    pub descriptor_bindings: Vec<Descriptor_binding>,
+    pub push_constants: Option<Push_constant_range>,
+    pub vertex_inputs: Vec<Interface_variable>,
+}
+
+#[derive(Default)]
+struct Spirv_module{
+    names: HashMap<u32, String>,
+    decorations: HashMap<u32, HashMap<u32, u32>>,
+    member_decorations: HashMap<(u32, u32), HashMap<u32, u32>>,
+    types: HashMap<u32, Spirv_type>,
+    constants: HashMap<u32, u32>,
+    //Id, pointer type and storage class
+    variables: Vec<(u32, u32, u32)>,
+    //Execution model, name and interface
+    entry_points: Vec<(u32, String, Vec<u32>)>,
+}
+
+//Impls
+impl Shader_reflection{
+    //Reflects the first entry point of a module
+    pub fn new(code: &[u32]) -> Result<Self, Reflection_error>{
+        let module = Spirv_module::parse(code)?;
+        let entry_point = module.entry_points.first().ok_or(Reflection_error::Missing_entry_point)?;
+
+        module.reflect(entry_point)
+    }
+
+    //Reflects the entry point whose execution model is the given stage
+    pub fn new_for_stage(code: &[u32], stage: vk::ShaderStageFlags) -> Result<Self, Reflection_error>{
+        let module = Spirv_module::parse(code)?;
+        let entry_point = module.entry_points.iter().find(|(model, _, _)| get_stage(*model) == stage).ok_or(Reflection_error::Missing_stage(stage))?;
+
+        module.reflect(entry_point)
+    }
+}
+
+impl Pipeline_reflection{
+    //Stages are given in pipeline order, the outputs of each have to feed the inputs of the next
+    pub fn new(stages: &[Shader_reflection]) -> Result<Self, Reflection_error>{
+        for pair in stages.windows(2) {
+            let (producer, consumer) = (&pair[0], &pair[1]);
+
+            for input in &consumer.inputs {
+                let output = producer.outputs.iter().find(|output| output.location == input.location).map(|output| output.format);
+                if !output.is_some_and(|output| is_interface_compatible(output, input.format)) {
+                    return Err(Reflection_error::Interface_mismatch{location: input.location, output, input: input.format})
+                }
+            }
+        }
+
+        let mut descriptor_bindings: Vec<Descriptor_binding> = vec![];
+        for binding in stages.iter().flat_map(|stage| &stage.descriptor_bindings) {
+            match descriptor_bindings.iter_mut().find(|existing| existing.set == binding.set && existing.binding == binding.binding) {
+                Some(existing) if existing.descriptor_type == binding.descriptor_type && existing.count == binding.count => existing.stages |= binding.stages,
+                Some(_) => return Err(Reflection_error::Descriptor_conflict{set: binding.set, binding: binding.binding}),
+                None => descriptor_bindings.push(*binding),
+            }
+        }
+        descriptor_bindings.sort_by_key(|binding| (binding.set, binding.binding));
+
+        //A single range covering every stage keeps the layout valid however the blocks overlap
+        let push_constants = stages.iter().filter_map(|stage| stage.push_constants).reduce(|merged, range| {
+            let offset = merged.offset.min(range.offset);
+            let end = (merged.offset + merged.size).max(range.offset + range.size);
+
+            Push_constant_range{
+                offset,
+                size: end - offset,
+                stages: merged.stages | range.stages,
+            }
+        });
+
+        let vertex_inputs = stages.iter().find(|stage| stage.stage == vk::ShaderStageFlags::VERTEX)
+            .map(|stage| stage.inputs.clone())
+            .unwrap_or_default();
+
+        Ok(Pipeline_reflection{
+            entry_points: stages.iter().map(|stage| stage.entry_point.clone()).collect(),
+            descriptor_bindings, push_constants, vertex_inputs
+        })
+    }
+
+    //The merged range has to end within the device's maxPushConstantsSize
+    pub fn check_push_constants(&self, max_push_constants_size: u32) -> Result<(), Reflection_error>{
+        match self.push_constants {
+            Some(range) if range.offset + range.size > max_push_constants_size => Err(Reflection_error::Push_constants_too_large{end: range.offset + range.size, max: max_push_constants_size}),
+            _ => Ok(()),
+        }
+    }
+
+    //Sets are numbered from zero, gaps still need an empty layout
+    pub fn get_set_count(&self) -> u32{
+        self.descriptor_bindings.iter().map(|binding| binding.set + 1).max().unwrap_or(0)
+    }
+
+    //Attributes are tightly packed into binding 0 in location order
+    pub(crate) fn get_vertex_input(&self) -> (Vec<vk::VertexInputBindingDescription>, Vec<vk::VertexInputAttributeDescription>){
+        let mut stride = 0;
+        let attributes = self.vertex_inputs.iter().map(|input| {
+            let attribute = vk::VertexInputAttributeDescription::default()
+                .location(input.location)
+                .binding(0)
+                .format(input.format)
+                .offset(stride);
+            stride += get_format_size(input.format);
+
+            attribute
+        }).collect::<Vec<_>>();
+
+        let bindings = if attributes.is_empty() {
+            vec![]
+        }
+        else {
+            vec![
+                vk::VertexInputBindingDescription::default()
+                    .binding(0)
+                    .stride(stride)
+                    .input_rate(vk::VertexInputRate::VERTEX)
+            ]
+        };
+
+        (bindings, attributes)
+    }
+}
+
+impl Spirv_module{
+    fn parse(code: &[u32]) -> Result<Self, Reflection_error>{
+        if code.len() < SPIRV_HEADER_WORDS || code[0] != SPIRV_MAGIC {
+            return Err(Reflection_error::Malformed{offset: 0})
+        }
+
+        let mut module = Spirv_module::default();
+        let mut offset = SPIRV_HEADER_WORDS;
+
+        while offset < code.len() {
+            let word_count = (code[offset] >> 16) as usize;
+            let opcode = code[offset] & 0xffff;
+            if word_count == 0 || offset + word_count > code.len() {
+                return Err(Reflection_error::Malformed{offset})
+            }
+
+            let operands = &code[offset+1..offset+word_count];
+            let operand = |index: usize| operands.get(index).copied().ok_or(Reflection_error::Malformed{offset});
+
+            match opcode {
+                OP_NAME => {
+                    module.names.insert(operand(0)?, read_string(operands.get(1..).unwrap_or_default()).0);
+                },
+                OP_ENTRY_POINT => {
+                    let (name, length) = read_string(operands.get(2..).unwrap_or_default());
+                    module.entry_points.push((operand(0)?, name, operands.get(2+length..).unwrap_or_default().to_vec()));
+                },
+                OP_DECORATE => {
+                    module.decorations.entry(operand(0)?).or_default().insert(operand(1)?, operand(2).unwrap_or(0));
+                },
+                OP_MEMBER_DECORATE => {
+                    module.member_decorations.entry((operand(0)?, operand(1)?)).or_default().insert(operand(2)?, operand(3).unwrap_or(0));
+                },
+                OP_TYPE_INT => {
+                    let kind = if operand(2)? == 0 { Scalar_kind::Uint } else { Scalar_kind::Sint };
+                    module.types.insert(operand(0)?, Spirv_type::Scalar{kind, width: operand(1)?});
+                },
+                OP_TYPE_FLOAT => {
+                    module.types.insert(operand(0)?, Spirv_type::Scalar{kind: Scalar_kind::Float, width: operand(1)?});
+                },
+                OP_TYPE_VECTOR => {
+                    module.types.insert(operand(0)?, Spirv_type::Vector{component: operand(1)?, count: operand(2)?});
+                },
+                OP_TYPE_MATRIX => {
+                    module.types.insert(operand(0)?, Spirv_type::Matrix{column: operand(1)?, count: operand(2)?});
+                },
+                OP_TYPE_IMAGE => {
+                    module.types.insert(operand(0)?, Spirv_type::Image{dim: operand(2)?, sampled: operand(6)?});
+                },
+                OP_TYPE_SAMPLER => {
+                    module.types.insert(operand(0)?, Spirv_type::Sampler);
+                },
+                OP_TYPE_SAMPLED_IMAGE => {
+                    module.types.insert(operand(0)?, Spirv_type::Sampled_image);
+                },
+                OP_TYPE_ARRAY => {
+                    module.types.insert(operand(0)?, Spirv_type::Array{element: operand(1)?, length: operand(2)?});
+                },
+                OP_TYPE_RUNTIME_ARRAY => {
+                    module.types.insert(operand(0)?, Spirv_type::Runtime_array{element: operand(1)?});
+                },
+                OP_TYPE_STRUCT => {
+                    module.types.insert(operand(0)?, Spirv_type::Struct{members: operands.get(1..).unwrap_or_default().to_vec()});
+                },
+                OP_TYPE_POINTER => {
+                    module.types.insert(operand(0)?, Spirv_type::Pointer{pointee: operand(2)?});
+                },
+                OP_TYPE_ACCELERATION_STRUCTURE => {
+                    module.types.insert(operand(0)?, Spirv_type::Acceleration_structure);
+                },
+                //Only the low word matters for array lengths
+                OP_CONSTANT => {
+                    module.constants.insert(operand(1)?, operand(2)?);
+                },
+                OP_VARIABLE => {
+                    module.variables.push((operand(1)?, operand(0)?, operand(2)?));
+                },
+                _ => (),
+            }
+
+            offset += word_count;
+        }
+
+        Ok(module)
+    }
+
+    //The interface limits inputs and outputs to the ones the entry point uses
+    fn reflect(&self, (model, entry_point, interface): &(u32, String, Vec<u32>)) -> Result<Shader_reflection, Reflection_error>{
+        let stage = get_stage(*model);
+
+        let mut reflection = Shader_reflection{
+            stage,
+            entry_point: entry_point.clone(),
+            descriptor_bindings: vec![],
+            push_constants: None,
+            inputs: vec![],
+            outputs: vec![],
+        };
+
+        for &(id, type_id, storage_class) in &self.variables {
+            let Some(&Spirv_type::Pointer{pointee}) = self.types.get(&type_id) else {
+                continue
+            };
+
+            match storage_class {
+                STORAGE_INPUT if interface.contains(&id) => reflection.inputs.extend(self.get_interface_variables(id, pointee)),
+                STORAGE_OUTPUT if interface.contains(&id) => reflection.outputs.extend(self.get_interface_variables(id, pointee)),
+                STORAGE_UNIFORM_CONSTANT | STORAGE_UNIFORM | STORAGE_STORAGE_BUFFER => {
+                    if let Some(binding) = self.get_descriptor_binding(id, pointee, storage_class, stage)? {
+                        reflection.descriptor_bindings.push(binding);
+                    }
+                },
+                STORAGE_PUSH_CONSTANT => reflection.push_constants = self.get_push_constants(pointee, stage),
+                _ => (),
+            }
+        }
+
+        reflection.inputs.sort_by_key(|variable| variable.location);
+        reflection.outputs.sort_by_key(|variable| variable.location);
+        reflection.descriptor_bindings.sort_by_key(|binding| (binding.set, binding.binding));
+
+        Ok(reflection)
+    }
+
+    fn get_decoration(&self, id: u32, decoration: u32) -> Option<u32>{
+        self.decorations.get(&id).and_then(|decorations| decorations.get(&decoration)).copied()
+    }
+
+    fn get_member_decoration(&self, id: u32, member: usize, decoration: u32) -> Option<u32>{
+        self.member_decorations.get(&(id, member as u32)).and_then(|decorations| decorations.get(&decoration)).copied()
+    }
+
+    //Built-ins, including the gl_PerVertex block, take no location
+    fn get_interface_variables(&self, id: u32, type_id: u32) -> Vec<Interface_variable>{
+        let is_built_in = self.get_decoration(id, DECORATION_BUILT_IN).is_some() || match self.types.get(&type_id) {
+            Some(Spirv_type::Struct{members}) => (0..members.len()).any(|member| self.get_member_decoration(type_id, member, DECORATION_BUILT_IN).is_some()),
+            _ => false,
+        };
+        let Some(location) = self.get_decoration(id, DECORATION_LOCATION).filter(|_| !is_built_in) else {
+            return vec![]
+        };
+
+        self.get_location_formats(type_id).into_iter().enumerate().map(|(index, format)| Interface_variable{
+            location: location + index as u32,
+            format,
+            name: self.names.get(&id).cloned(),
+        }).collect()
+    }
+
+    fn get_location_formats(&self, type_id: u32) -> Vec<vk::Format>{
+        match self.types.get(&type_id) {
+            Some(Spirv_type::Scalar{..}) => vec![self.get_format(type_id, 1)],
+            Some(Spirv_type::Vector{component, count}) => vec![self.get_format(*component, *count)],
+            Some(Spirv_type::Matrix{column, count}) => self.get_location_formats(*column).repeat(*count as usize),
+            Some(Spirv_type::Array{element, length}) => self.get_location_formats(*element).repeat(self.constants.get(length).copied().unwrap_or(1) as usize),
+            _ => vec![vk::Format::UNDEFINED],
+        }
+    }
+
+    fn get_format(&self, component: u32, count: u32) -> vk::Format{
+        let Some(&Spirv_type::Scalar{kind, width}) = self.types.get(&component) else {
+            return vk::Format::UNDEFINED
+        };
+
+        ATTRIBUTE_FORMATS.iter().find(|(format_kind, format_width, _)| *format_kind == kind && *format_width == width)
+            .and_then(|(_, _, formats)| formats.get((count as usize).wrapping_sub(1)))
+            .copied()
+            .unwrap_or(vk::Format::UNDEFINED)
+    }
+
+    fn get_descriptor_binding(&self, id: u32, type_id: u32, storage_class: u32, stage: vk::ShaderStageFlags) -> Result<Option<Descriptor_binding>, Reflection_error>{
+        let (Some(set), Some(binding)) = (self.get_decoration(id, DECORATION_DESCRIPTOR_SET), self.get_decoration(id, DECORATION_BINDING)) else {
+            return Ok(None)
+        };
+
+        //Arrays of descriptors become the descriptor count, runtime sized ones count as one
+        let mut type_id = type_id;
+        let mut count = 1;
+        loop {
+            match self.types.get(&type_id) {
+                Some(Spirv_type::Array{element, length}) => {
+                    count *= self.constants.get(length).copied().unwrap_or(1);
+                    type_id = *element;
+                },
+                Some(Spirv_type::Runtime_array{element}) => type_id = *element,
+                _ => break,
+            }
+        }
+
+        let descriptor_type = match (storage_class, self.types.get(&type_id)) {
+            (_, Some(Spirv_type::Sampler)) => vk::DescriptorType::SAMPLER,
+            (_, Some(Spirv_type::Sampled_image)) => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            (_, Some(Spirv_type::Image{dim: DIM_BUFFER, sampled: IMAGE_STORAGE})) => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+            (_, Some(Spirv_type::Image{dim: DIM_BUFFER, ..})) => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+            (_, Some(Spirv_type::Image{dim: DIM_SUBPASS_DATA, ..})) => vk::DescriptorType::INPUT_ATTACHMENT,
+            (_, Some(Spirv_type::Image{sampled: IMAGE_STORAGE, ..})) => vk::DescriptorType::STORAGE_IMAGE,
+            (_, Some(Spirv_type::Image{..})) => vk::DescriptorType::SAMPLED_IMAGE,
+            (_, Some(Spirv_type::Acceleration_structure)) => vk::DescriptorType::ACCELERATION_STRUCTURE_KHR,
+            (STORAGE_STORAGE_BUFFER, Some(Spirv_type::Struct{..})) => vk::DescriptorType::STORAGE_BUFFER,
+            (STORAGE_UNIFORM, Some(Spirv_type::Struct{..})) if self.get_decoration(type_id, DECORATION_BUFFER_BLOCK).is_some() => vk::DescriptorType::STORAGE_BUFFER,
+            (STORAGE_UNIFORM, Some(Spirv_type::Struct{..})) => vk::DescriptorType::UNIFORM_BUFFER,
+            _ => return Err(Reflection_error::Unsupported_descriptor{set, binding}),
+        };
+
+        Ok(Some(Descriptor_binding{
+            set, binding, descriptor_type, count,
+            stages: stage,
+        }))
+    }
+
+    fn get_push_constants(&self, type_id: u32, stage: vk::ShaderStageFlags) -> Option<Push_constant_range>{
+        let Some(Spirv_type::Struct{members}) = self.types.get(&type_id) else {
+            return None
+        };
+
+        let (start, end) = members.iter().enumerate().map(|(index, &member)| {
+            let offset = self.get_member_decoration(type_id, index, DECORATION_OFFSET).unwrap_or(0);
+            let matrix_stride = self.get_member_decoration(type_id, index, DECORATION_MATRIX_STRIDE);
+
+            (offset, offset + self.get_size(member, matrix_stride))
+        }).fold((u32::MAX, 0), |(start, end), (offset, member_end)| (start.min(offset), end.max(member_end)));
+
+        //Vulkan wants both offset and size as multiples of four
+        let offset = start & !3;
+        (end > offset).then(|| Push_constant_range{
+            offset,
+            size: (end - offset).next_multiple_of(4),
+            stages: stage,
+        })
+    }
+
+    fn get_size(&self, type_id: u32, matrix_stride: Option<u32>) -> u32{
+        match self.types.get(&type_id) {
+            Some(Spirv_type::Scalar{width, ..}) => width / 8,
+            Some(Spirv_type::Vector{component, count}) => count * self.get_size(*component, None),
+            Some(Spirv_type::Matrix{column, count}) => count * matrix_stride.unwrap_or_else(|| self.get_size(*column, None)),
+            Some(Spirv_type::Array{element, length}) => {
+                let stride = self.get_decoration(type_id, DECORATION_ARRAY_STRIDE).unwrap_or_else(|| self.get_size(*element, matrix_stride));
+                self.constants.get(length).copied().unwrap_or(0) * stride
+            },
+            Some(Spirv_type::Struct{members}) => members.iter().enumerate().map(|(index, &member)| {
+                let offset = self.get_member_decoration(type_id, index, DECORATION_OFFSET).unwrap_or(0);
+                offset + self.get_size(member, self.get_member_decoration(type_id, index, DECORATION_MATRIX_STRIDE))
+            }).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+}
+
+impl Display for Reflection_error{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed{offset} => write!(f, "malformed SPIR-V instruction at word {}", offset),
+            Self::Missing_entry_point => write!(f, "module has no entry point"),
+            Self::Missing_stage(stage) => write!(f, "module has no {:?} entry point", stage),
+            Self::Unsupported_descriptor{set, binding} => write!(f, "descriptor at set {} binding {} has an unsupported type", set, binding),
+            Self::Descriptor_conflict{set, binding} => write!(f, "stages disagree on the descriptor at set {} binding {}", set, binding),
+            Self::Interface_mismatch{location, output: Some(output), input} => write!(f, "input at location {} expects {:?} but the previous stage writes {:?}", location, input, output),
+            Self::Interface_mismatch{location, output: None, input} => write!(f, "input at location {} expects {:?} but the previous stage writes nothing", location, input),
+            Self::Push_constants_too_large{end, max} => write!(f, "push constants end at byte {}, the device allows {}", end, max),
+        }
+    }
+}
+
+impl std::error::Error for Reflection_error{}
+
+//Functions
+fn read_string(words: &[u32]) -> (String, usize){
+    let mut bytes = vec![];
+
+    for (index, word) in words.iter().enumerate() {
+        for byte in word.to_le_bytes() {
+            if byte == 0 {
+                return (String::from_utf8_lossy(&bytes).into_owned(), index + 1)
+            }
+            bytes.push(byte);
+        }
+    }
+
+    (String::from_utf8_lossy(&bytes).into_owned(), words.len())
+}
+
+fn get_stage(execution_model: u32) -> vk::ShaderStageFlags{
+    match execution_model {
+        0 => vk::ShaderStageFlags::VERTEX,
+        1 => vk::ShaderStageFlags::TESSELLATION_CONTROL,
+        2 => vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+        3 => vk::ShaderStageFlags::GEOMETRY,
+        4 => vk::ShaderStageFlags::FRAGMENT,
+        5 => vk::ShaderStageFlags::COMPUTE,
+        5364 => vk::ShaderStageFlags::TASK_EXT,
+        5365 => vk::ShaderStageFlags::MESH_EXT,
+        5313 => vk::ShaderStageFlags::RAYGEN_KHR,
+        5314 => vk::ShaderStageFlags::INTERSECTION_KHR,
+        5315 => vk::ShaderStageFlags::ANY_HIT_KHR,
+        5316 => vk::ShaderStageFlags::CLOSEST_HIT_KHR,
+        5317 => vk::ShaderStageFlags::MISS_KHR,
+        5318 => vk::ShaderStageFlags::CALLABLE_KHR,
+        _ => vk::ShaderStageFlags::empty(),
+    }
+}
+
+fn get_format_info(format: vk::Format) -> Option<(Scalar_kind, u32, u32)>{
+    ATTRIBUTE_FORMATS.iter().find_map(|(kind, width, formats)| {
+        formats.iter().position(|&candidate| candidate == format).map(|index| (*kind, *width, index as u32 + 1))
+    })
+}
+
+fn get_format_size(format: vk::Format) -> u32{
+    get_format_info(format).map(|(_, width, count)| width / 8 * count).unwrap_or(0)
+}
+
+//An output may carry more components than the input reads, as long as the component type agrees
+fn is_interface_compatible(output: vk::Format, input: vk::Format) -> bool{
+    match (get_format_info(output), get_format_info(input)) {
+        (Some((output_kind, output_width, output_count)), Some((input_kind, input_width, input_count))) => {
+            output_kind == input_kind && output_width == input_width && output_count >= input_count
+        },
+        _ => output == input,
+    }
+}
@@ -0,0 +1,155 @@
+//Made by Han_feng
+
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::libs::shaders::{load_spirv, Shader_error};
+
+//Consts
+pub const SHADER_HASH_SIZE: usize = 32;
+//Manifests refer to stored shaders as sha256:<hex> instead of a name
+pub const SHADER_HASH_PREFIX: &str = "sha256:";
+
+//Enums
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Shader_store_error{
+    No_store,
+    Io(String),
+    Invalid_module(Shader_error),
+    Invalid_hash(String),
+    Missing(Shader_hash),
+    Hash_mismatch{expected: Shader_hash, found: Shader_hash},
+}
+
+//Structs
+//SHA-256 of the module words in little endian, so a byte swapped copy has the same hash
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Shader_hash(pub [u8; SHADER_HASH_SIZE]);
+
+//Modules are kept as <hash>.spv and verified against their hash whenever they are read back
+#[derive(Debug, Clone)]
+pub struct Shader_store{
+    path: PathBuf,
+}
+
+//Impls
+impl Shader_hash{
+    pub fn new(code: &[u32]) -> Self{
+        let mut hasher = Sha256::new();
+        code.iter().for_each(|word| hasher.update(word.to_le_bytes()));
+
+        Shader_hash(hasher.finalize().into())
+    }
+
+    //Accepts the hex digest with or without the sha256: prefix
+    pub fn parse(text: &str) -> Result<Self, Shader_store_error>{
+        let digest = text.strip_prefix(SHADER_HASH_PREFIX).unwrap_or(text);
+        let invalid = || Shader_store_error::Invalid_hash(text.to_string());
+
+        if digest.len() != SHADER_HASH_SIZE*2 {
+            return Err(invalid())
+        }
+
+        let mut hash = [0; SHADER_HASH_SIZE];
+        for (index, byte) in hash.iter_mut().enumerate() {
+            let pair = digest.get(index*2..index*2+2).ok_or_else(invalid)?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| invalid())?;
+        }
+
+        Ok(Shader_hash(hash))
+    }
+}
+
+impl Display for Shader_hash{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+impl From<Shader_hash> for String{
+    fn from(hash: Shader_hash) -> Self {
+        hash.to_string()
+    }
+}
+
+impl TryFrom<String> for Shader_hash{
+    type Error = Shader_store_error;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        Shader_hash::parse(&text)
+    }
+}
+
+impl Shader_store{
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Shader_store_error>{
+        let path = path.as_ref().to_path_buf();
+        fs::create_dir_all(&path).map_err(|error| Shader_store_error::Io(format!("{}: {}", path.display(), error)))?;
+
+        Ok(Shader_store{
+            path
+        })
+    }
+
+    //Storing a module again only replaces the file when the stored copy does not verify
+    pub fn insert(&self, data: &[u8]) -> Result<Shader_hash, Shader_store_error>{
+        let code = load_spirv(data).map_err(Shader_store_error::Invalid_module)?;
+        let hash = Shader_hash::new(&code);
+        if self.load(&hash).is_ok() {
+            return Ok(hash)
+        }
+
+        //Write beside the target first so that a crash never leaves a half written module behind
+        let path = self.get_path(&hash);
+        let temp_path = path.with_extension("tmp");
+        let data = code.iter().flat_map(|word| word.to_le_bytes()).collect::<Vec<_>>();
+        fs::write(&temp_path, &data)
+            .and_then(|_| fs::rename(&temp_path, &path))
+            .map_err(|error| Shader_store_error::Io(format!("{}: {}", path.display(), error)))?;
+
+        Ok(hash)
+    }
+
+    pub fn load(&self, hash: &Shader_hash) -> Result<Vec<u32>, Shader_store_error>{
+        let path = self.get_path(hash);
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Err(Shader_store_error::Missing(*hash)),
+            Err(error) => return Err(Shader_store_error::Io(format!("{}: {}", path.display(), error))),
+        };
+
+        let code = load_spirv(&data).map_err(Shader_store_error::Invalid_module)?;
+        let found = Shader_hash::new(&code);
+        if found != *hash {
+            return Err(Shader_store_error::Hash_mismatch{expected: *hash, found})
+        }
+
+        Ok(code)
+    }
+
+    pub fn contains(&self, hash: &Shader_hash) -> bool{
+        self.get_path(hash).is_file()
+    }
+
+    pub fn get_path(&self, hash: &Shader_hash) -> PathBuf{
+        self.path.join(format!("{}.spv", hash))
+    }
+}
+
+impl Display for Shader_store_error{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::No_store => write!(f, "no shader store is open"),
+            Self::Io(message) => write!(f, "shader store io error ({})", message),
+            Self::Invalid_module(error) => write!(f, "invalid shader module ({})", error),
+            Self::Invalid_hash(text) => write!(f, "invalid shader hash {}", text),
+            Self::Missing(hash) => write!(f, "shader {} is not in the store", hash),
+            Self::Hash_mismatch{expected, found} => write!(f, "stored shader {} hashes to {}", expected, found),
+        }
+    }
+}
+
+impl std::error::Error for Shader_store_error{}
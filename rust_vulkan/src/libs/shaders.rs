@@ -0,0 +1,233 @@
+//Made by Han_feng
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use ash::vk;
+use crate::libs::shader_store::{Shader_hash, Shader_store, Shader_store_error, SHADER_HASH_PREFIX};
+use crate::libs::vulkan_application::API_VERSION;
+use crate::save_log;
+
+cfg_if::cfg_if! {
+    if #[cfg(debug_assertions)] {
+        use crate::libs::utils::Log_level;
+        use log::Level;
+    }
+}
+
+//Consts
+include!(concat!(env!("OUT_DIR"), "/shader_registry.rs"));
+const SHADER_REPORT: &str = include_str!(concat!(env!("OUT_DIR"), "/rust_vulkan_shaders.json"));
+
+pub(crate) const DEFAULT_VERTEX_SHADER: &str = "vertex_shader";
+pub(crate) const DEFAULT_FRAGMENT_SHADER: &str = "fragment_shader";
+
+pub const SPIRV_MAGIC: u32 = 0x07230203;
+const SPIRV_HEADER_SIZE: usize = 20;
+//Highest SPIR-V minor version each Vulkan 1.x consumes, the same mapping as TARGET_ENVS in build.rs
+const SPIRV_MINOR_VERSIONS: [u32; 4] = [0, 3, 5, 6];
+const MAX_SPIRV_MINOR_VERSION: u32 = SPIRV_MINOR_VERSIONS[vk::api_version_minor(API_VERSION) as usize];
+
+//Types
+pub(crate) type Shader_code = Arc<[u32]>;
+
+//Enums
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub enum Shader_stage{
+    Vertex = 0,
+    Fragment = 1,
+    Compute = 2,
+    Geometry = 3,
+    Tess_control = 4,
+    Tess_evaluation = 5,
+    Task = 6,
+    Mesh = 7,
+    Ray_generation = 8,
+    Any_hit = 9,
+    Closest_hit = 10,
+    Miss = 11,
+    Intersection = 12,
+    Callable = 13,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Shader_error{
+    Io(String),
+    Truncated{size: usize},
+    Misaligned{size: usize},
+    Invalid_magic(u32),
+    Unsupported_version{major: u32, minor: u32},
+}
+
+//Structs
+#[derive(Debug)]
+pub struct Embedded_shader{
+    pub name: &'static str,
+    pub stage: Shader_stage,
+    //Features this variant was compiled with, empty for the plain shader
+    pub features: &'static [&'static str],
+    pub code: &'static [u8],
+}
+
+//Runtime modules shadow the embedded ones with the same name, sha256:<hex> names are read from the store
+#[derive(Default)]
+pub(crate) struct Shader_library{
+    modules: HashMap<String, Shader_code>,
+    store: Option<Shader_store>,
+}
+
+//Impls
+impl Shader_library{
+    pub(crate) fn register(&mut self, name: &str, data: &[u8]) -> Result<(), Shader_error>{
+        let code = load_spirv(data)?;
+        self.modules.insert(name.to_string(), code.into());
+
+        Ok(())
+    }
+
+    pub(crate) fn register_file(&mut self, name: &str, path: impl AsRef<Path>) -> Result<(), Shader_error>{
+        let data = fs::read(path.as_ref()).map_err(|error| Shader_error::Io(format!("{}: {}", path.as_ref().display(), error)))?;
+
+        self.register(name, &data)
+    }
+
+    pub(crate) fn open_store(&mut self, path: impl AsRef<Path>) -> Result<(), Shader_store_error>{
+        self.store = Some(Shader_store::open(path)?);
+
+        Ok(())
+    }
+
+    pub(crate) fn store(&self, data: &[u8]) -> Result<Shader_hash, Shader_store_error>{
+        match &self.store {
+            Some(store) => store.insert(data),
+            None => Err(Shader_store_error::No_store),
+        }
+    }
+
+    //Features a shader does not declare are ignored, so one feature set can serve every stage of a pipeline
+    //Runtime modules carry no stage and are returned whatever stage is asked for
+    pub(crate) fn get_variant(&self, name: &str, stage: Shader_stage, features: &[String]) -> Option<Shader_code>{
+        if name.starts_with(SHADER_HASH_PREFIX) {
+            return self.get_stored(name)
+        }
+        if let Some(code) = self.modules.get(name) {
+            return Some(code.clone())
+        }
+
+        let declared = EMBEDDED_SHADERS.iter().filter(|shader| shader.name == name && shader.stage == stage).flat_map(|shader| shader.features.iter().copied()).collect::<HashSet<_>>();
+        let features = features.iter().map(|feature| feature.as_str()).filter(|feature| declared.contains(feature)).collect::<Vec<_>>();
+
+        EMBEDDED_SHADERS.iter().find(|shader| shader.stage == stage && is_variant(shader, name, &features)).map(get_shader_code)
+    }
+
+    //Stored modules are verified against their hash on every load
+    #[allow(unused_variables)]
+    fn get_stored(&self, name: &str) -> Option<Shader_code>{
+        let result = match &self.store {
+            Some(store) => Shader_hash::parse(name).and_then(|hash| store.load(&hash)),
+            None => Err(Shader_store_error::No_store),
+        };
+
+        match result {
+            Ok(code) => Some(code.into()),
+            Err(error) => {
+                save_log!(Log_level::General, Level::Error, "Failed to load shader {}: {}", name, error);
+                None
+            }
+        }
+    }
+}
+
+impl From<Shader_stage> for vk::ShaderStageFlags{
+    fn from(stage: Shader_stage) -> Self {
+        match stage {
+            Shader_stage::Vertex => vk::ShaderStageFlags::VERTEX,
+            Shader_stage::Fragment => vk::ShaderStageFlags::FRAGMENT,
+            Shader_stage::Compute => vk::ShaderStageFlags::COMPUTE,
+            Shader_stage::Geometry => vk::ShaderStageFlags::GEOMETRY,
+            Shader_stage::Tess_control => vk::ShaderStageFlags::TESSELLATION_CONTROL,
+            Shader_stage::Tess_evaluation => vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+            Shader_stage::Task => vk::ShaderStageFlags::TASK_EXT,
+            Shader_stage::Mesh => vk::ShaderStageFlags::MESH_EXT,
+            Shader_stage::Ray_generation => vk::ShaderStageFlags::RAYGEN_KHR,
+            Shader_stage::Any_hit => vk::ShaderStageFlags::ANY_HIT_KHR,
+            Shader_stage::Closest_hit => vk::ShaderStageFlags::CLOSEST_HIT_KHR,
+            Shader_stage::Miss => vk::ShaderStageFlags::MISS_KHR,
+            Shader_stage::Intersection => vk::ShaderStageFlags::INTERSECTION_KHR,
+            Shader_stage::Callable => vk::ShaderStageFlags::CALLABLE_KHR,
+        }
+    }
+}
+
+impl Display for Shader_error{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(message) => write!(f, "failed to read shader ({})", message),
+            Self::Truncated{size} => write!(f, "{} bytes are too short for a SPIR-V header", size),
+            Self::Misaligned{size} => write!(f, "{} bytes are not a whole number of SPIR-V words", size),
+            Self::Invalid_magic(magic) => write!(f, "invalid SPIR-V magic number {:#010x}", magic),
+            Self::Unsupported_version{major, minor} => write!(f, "unsupported SPIR-V version {}.{}", major, minor),
+        }
+    }
+}
+
+impl std::error::Error for Shader_error{}
+
+//Functions
+//Copies into words so the module is aligned whatever buffer it came from, byte swapped modules are accepted as well
+pub fn load_spirv(data: &[u8]) -> Result<Vec<u32>, Shader_error>{
+    if !data.len().is_multiple_of(4) {
+        return Err(Shader_error::Misaligned{size: data.len()})
+    }
+    if data.len() < SPIRV_HEADER_SIZE {
+        return Err(Shader_error::Truncated{size: data.len()})
+    }
+
+    let mut code = data.chunks_exact(4).map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]])).collect::<Vec<_>>();
+    if code[0] == SPIRV_MAGIC.swap_bytes() {
+        code.iter_mut().for_each(|word| *word = word.swap_bytes());
+    }
+    if code[0] != SPIRV_MAGIC {
+        return Err(Shader_error::Invalid_magic(code[0]))
+    }
+
+    let (major, minor) = ((code[1] >> 16) & 0xff, (code[1] >> 8) & 0xff);
+    if major != 1 || minor > MAX_SPIRV_MINOR_VERSION {
+        return Err(Shader_error::Unsupported_version{major, minor})
+    }
+
+    Ok(code)
+}
+
+//JSON report written by build.rs, with sizes, instruction counts and entry points of every embedded shader
+pub fn get_shader_report() -> &'static str{
+    SHADER_REPORT
+}
+
+pub fn get_embedded_shaders() -> &'static [Embedded_shader]{
+    EMBEDDED_SHADERS
+}
+
+pub fn find_embedded_shader(name: &str) -> Option<&'static Embedded_shader>{
+    find_embedded_shader_variant(name, &[])
+}
+
+//The feature set is matched regardless of order, a name shared by several stages finds the first in file name order
+pub fn find_embedded_shader_variant(name: &str, features: &[&str]) -> Option<&'static Embedded_shader>{
+    EMBEDDED_SHADERS.iter().find(|shader| is_variant(shader, name, features))
+}
+
+pub(crate) fn get_embedded_shader(name: &str) -> Option<Shader_code>{
+    find_embedded_shader(name).map(get_shader_code)
+}
+
+fn is_variant(shader: &Embedded_shader, name: &str, features: &[&str]) -> bool{
+    shader.name == name && shader.features.len() == features.len() && features.iter().all(|feature| shader.features.contains(feature))
+}
+
+fn get_shader_code(shader: &Embedded_shader) -> Shader_code{
+    load_spirv(shader.code).expect("embedded shaders are compiled by build.rs").into()
+}
@@ -0,0 +1,268 @@
+//Made by Han_feng
+
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use ash::vk;
+use crate::libs::buffers::{submit_once, Buffer};
+use crate::libs::memory_allocator::{Allocation, Memory_allocator, Memory_usage, Pool_kind};
+use crate::libs::utils::Vulkan_result;
+use crate::save_log;
+
+cfg_if::cfg_if! {
+    if #[cfg(debug_assertions)] {
+        use crate::libs::utils::Log_level;
+        use log::Level;
+    }
+}
+
+//Consts
+//Photos are stored in sRGB, sampling converts them back to linear
+const TEXTURE_FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+
+//Enums
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Texture_error{
+    Io(String),
+    //Only JPEG and PNG are decoded
+    Unsupported_format,
+    Decode(String),
+}
+
+//Structs
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Texture_handle(pub u64);
+
+//Decoded RGBA8 pixels, rows are tightly packed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image_data{
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+//A sampled image, kept in SHADER_READ_ONLY_OPTIMAL once uploaded
+pub(crate) struct Texture{
+    image: vk::Image,
+    allocation: Allocation,
+    pub(crate) view: vk::ImageView,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+//Destroyed textures leave an empty slot behind so handles stay stable
+#[derive(Default)]
+pub(crate) struct Textures{
+    textures: Vec<Option<Texture>>,
+    //Shared by every texture, created with the first one
+    sampler: Option<vk::Sampler>,
+}
+
+//Functions
+pub fn decode_image(data: &[u8]) -> Result<Image_data, Texture_error>{
+    let format = match image::guess_format(data) {
+        Ok(format @ (image::ImageFormat::Jpeg | image::ImageFormat::Png)) => format,
+        _ => return Err(Texture_error::Unsupported_format),
+    };
+
+    let image = image::load_from_memory_with_format(data, format).map_err(|error| Texture_error::Decode(error.to_string()))?.into_rgba8();
+
+    Ok(Image_data{
+        width: image.width(),
+        height: image.height(),
+        pixels: image.into_raw(),
+    })
+}
+
+pub fn decode_image_file(path: impl AsRef<Path>) -> Result<Image_data, Texture_error>{
+    let data = fs::read(path.as_ref()).map_err(|error| Texture_error::Io(format!("{}: {}", path.as_ref().display(), error)))?;
+
+    decode_image(&data)
+}
+
+//Impls
+impl Texture_handle{
+    pub const NONE: Self = Texture_handle(u64::MAX);
+}
+
+impl Texture{
+    fn new(device: &ash::Device, allocator: &mut Memory_allocator, pool: vk::CommandPool, queue: vk::Queue, image_data: &Image_data) -> Vulkan_result<Self>{
+        let extent = vk::Extent3D{
+            width: image_data.width,
+            height: image_data.height,
+            depth: 1,
+        };
+
+        let create_info = vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(TEXTURE_FORMAT)
+            .extent(extent)
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+
+        let image = unsafe { device.create_image(&create_info, None)? };
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+
+        let allocation = match allocator.allocate(device, requirements, Memory_usage::Gpu_only, Pool_kind::Optimal) {
+            Ok(allocation) => allocation,
+            Err(code) => {
+                unsafe { device.destroy_image(image, None) };
+                return Err(code)
+            }
+        };
+
+        let mut texture = Texture{
+            image, allocation,
+            view: vk::ImageView::null(),
+            width: image_data.width,
+            height: image_data.height,
+        };
+
+        let result = (|| -> Vulkan_result<vk::ImageView> {
+            unsafe { device.bind_image_memory(image, texture.allocation.memory, texture.allocation.offset)? };
+            Self::upload(device, allocator, pool, queue, image, extent, &image_data.pixels)?;
+
+            let view_info = vk::ImageViewCreateInfo::default()
+                .image(image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(TEXTURE_FORMAT)
+                .subresource_range(Self::get_subresource_range());
+
+            Ok(unsafe { device.create_image_view(&view_info, None)? })
+        })();
+
+        match result {
+            Ok(view) => {
+                texture.view = view;
+                Ok(texture)
+            },
+            Err(code) => {
+                texture.destroy(device, allocator);
+                Err(code)
+            }
+        }
+    }
+
+    //Copied through a staging buffer, the image ends up ready to be sampled by fragment shaders
+    fn upload(device: &ash::Device, allocator: &mut Memory_allocator, pool: vk::CommandPool, queue: vk::Queue, image: vk::Image, extent: vk::Extent3D, pixels: &[u8]) -> Vulkan_result<()>{
+        let staging = Buffer::new(device, allocator, pixels.len() as vk::DeviceSize, vk::BufferUsageFlags::TRANSFER_SRC, Memory_usage::Staging)?;
+
+        let result = staging.write(0, pixels).and_then(|_| submit_once(device, pool, queue, |command_buffer| unsafe {
+            let barrier = |old_layout, new_layout, src_access_mask, dst_access_mask| [
+                vk::ImageMemoryBarrier::default()
+                    .old_layout(old_layout)
+                    .new_layout(new_layout)
+                    .src_access_mask(src_access_mask)
+                    .dst_access_mask(dst_access_mask)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .image(image)
+                    .subresource_range(Self::get_subresource_range())
+            ];
+
+            let regions = [
+                vk::BufferImageCopy::default()
+                    .image_subresource(vk::ImageSubresourceLayers::default()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .layer_count(1))
+                    .image_extent(extent)
+            ];
+
+            device.cmd_pipeline_barrier(command_buffer, vk::PipelineStageFlags::TOP_OF_PIPE, vk::PipelineStageFlags::TRANSFER, vk::DependencyFlags::empty(), &[], &[],
+                &barrier(vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::AccessFlags::empty(), vk::AccessFlags::TRANSFER_WRITE));
+            device.cmd_copy_buffer_to_image(command_buffer, staging.buffer, image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, &regions);
+            device.cmd_pipeline_barrier(command_buffer, vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::FRAGMENT_SHADER, vk::DependencyFlags::empty(), &[], &[],
+                &barrier(vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ));
+        }));
+
+        staging.destroy(device, allocator);
+
+        result
+    }
+
+    fn get_subresource_range() -> vk::ImageSubresourceRange{
+        vk::ImageSubresourceRange::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .level_count(1)
+            .layer_count(1)
+    }
+
+    fn destroy(self, device: &ash::Device, allocator: &mut Memory_allocator){
+        unsafe {
+            device.destroy_image_view(self.view, None);
+            device.destroy_image(self.image, None);
+        }
+        allocator.free(device, self.allocation);
+    }
+}
+
+impl Textures{
+    pub(crate) fn create(&mut self, device: &ash::Device, allocator: &mut Memory_allocator, pool: vk::CommandPool, queue: vk::Queue, image_data: &Image_data) -> Vulkan_result<Texture_handle>{
+        if self.sampler.is_none() {
+            self.sampler = Some(Self::create_sampler(device)?);
+        }
+
+        let texture = Texture::new(device, allocator, pool, queue, image_data)?;
+        let handle = Texture_handle(self.textures.len() as u64);
+        self.textures.push(Some(texture));
+
+        save_log!(Log_level::General, Level::Info, "Created texture {} of {}x{}", handle.0, image_data.width, image_data.height);
+
+        Ok(handle)
+    }
+
+    pub(crate) fn get(&self, handle: Texture_handle) -> Option<&Texture>{
+        self.textures.get(handle.0 as usize)?.as_ref()
+    }
+
+    //Exists once a texture has been created
+    pub(crate) fn get_sampler(&self) -> vk::Sampler{
+        self.sampler.unwrap_or_default()
+    }
+
+    //The caller must make sure the device no longer uses the texture
+    pub(crate) fn destroy(&mut self, device: &ash::Device, allocator: &mut Memory_allocator, handle: Texture_handle) -> Option<()>{
+        self.textures.get_mut(handle.0 as usize)?.take()?.destroy(device, allocator);
+
+        Some(())
+    }
+
+    pub(crate) fn destroy_all(&mut self, device: &ash::Device, allocator: &mut Memory_allocator){
+        self.textures.drain(..).flatten().for_each(|texture| texture.destroy(device, allocator));
+
+        if let Some(sampler) = self.sampler.take() {
+            unsafe { device.destroy_sampler(sampler, None) };
+        }
+    }
+
+    fn create_sampler(device: &ash::Device) -> Vulkan_result<vk::Sampler>{
+        let create_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .max_lod(0.0);
+
+        Ok(unsafe { device.create_sampler(&create_info, None)? })
+    }
+}
+
+impl Display for Texture_error{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(message) => write!(f, "failed to read image ({})", message),
+            Self::Unsupported_format => write!(f, "only JPEG and PNG images are supported"),
+            Self::Decode(message) => write!(f, "failed to decode image ({})", message),
+        }
+    }
+}
+
+impl std::error::Error for Texture_error{}
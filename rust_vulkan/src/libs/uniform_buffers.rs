@@ -0,0 +1,124 @@
+//Made by Han_feng
+
+use ash::vk;
+use crate::libs::buffers::Buffer;
+use crate::libs::memory_allocator::{Memory_allocator, Memory_usage};
+use crate::libs::utils::{Status_code, Vulkan_result};
+use crate::save_log;
+
+cfg_if::cfg_if! {
+    if #[cfg(debug_assertions)] {
+        use crate::libs::utils::Log_level;
+        use log::Level;
+    }
+}
+
+//Structs
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Uniform_buffer_handle(pub u64);
+
+//Updates land in a host copy first, each frame's buffer takes it over once that frame's fence has signaled
+pub(crate) struct Uniform_buffer{
+    //One per frame in flight
+    buffers: Vec<Buffer>,
+    data: Vec<u8>,
+    version: u64,
+    //The version each frame's buffer holds
+    frame_versions: Vec<u64>,
+}
+
+//Destroyed buffers leave an empty slot behind so handles stay stable
+#[derive(Default)]
+pub(crate) struct Uniform_buffers{
+    buffers: Vec<Option<Uniform_buffer>>,
+}
+
+//Impls
+impl Uniform_buffer{
+    pub(crate) fn get_buffer(&self, frame: usize) -> vk::Buffer{
+        self.buffers[frame].buffer
+    }
+
+    pub(crate) fn get_size(&self) -> vk::DeviceSize{
+        self.data.len() as vk::DeviceSize
+    }
+
+    fn destroy(self, device: &ash::Device, allocator: &mut Memory_allocator){
+        self.buffers.into_iter().for_each(|buffer| buffer.destroy(device, allocator));
+    }
+}
+
+impl Uniform_buffers{
+    //Starts out zeroed
+    pub(crate) fn create(&mut self, device: &ash::Device, allocator: &mut Memory_allocator, size: vk::DeviceSize, frame_count: usize) -> Vulkan_result<Uniform_buffer_handle>{
+        let mut buffers = vec![];
+        for _ in 0..frame_count {
+            match Buffer::new(device, allocator, size, vk::BufferUsageFlags::UNIFORM_BUFFER, Memory_usage::Cpu_to_gpu) {
+                Ok(buffer) => buffers.push(buffer),
+                Err(code) => {
+                    buffers.into_iter().for_each(|buffer| buffer.destroy(device, allocator));
+                    return Err(code)
+                }
+            }
+        }
+
+        let handle = Uniform_buffer_handle(self.buffers.len() as u64);
+        self.buffers.push(Some(Uniform_buffer{
+            buffers,
+            data: vec![0; size as usize],
+            version: 1,
+            frame_versions: vec![0; frame_count],
+        }));
+
+        save_log!(Log_level::General, Level::Info, "Created uniform buffer {} of {} bytes", handle.0, size);
+
+        Ok(handle)
+    }
+
+    pub(crate) fn get(&self, handle: Uniform_buffer_handle) -> Option<&Uniform_buffer>{
+        self.buffers.get(handle.0 as usize)?.as_ref()
+    }
+
+    //The whole block is replaced, so data has to be exactly as large as the buffer
+    #[allow(unused_variables)]
+    pub(crate) fn update(&mut self, handle: Uniform_buffer_handle, data: &[u8]) -> Vulkan_result<()>{
+        let Some(uniform_buffer) = self.buffers.get_mut(handle.0 as usize).and_then(|buffer| buffer.as_mut()) else {
+            save_log!(Log_level::General, Level::Error, "Unknown uniform buffer {}", handle.0);
+            return Err(Status_code::Failure)
+        };
+
+        if data.len() != uniform_buffer.data.len() {
+            save_log!(Log_level::General, Level::Error, "Uniform buffer {} holds {} bytes, got {}", handle.0, uniform_buffer.data.len(), data.len());
+            return Err(Status_code::Failure)
+        }
+
+        uniform_buffer.data.copy_from_slice(data);
+        uniform_buffer.version += 1;
+
+        Ok(())
+    }
+
+    //Called once the device is done with the frame
+    pub(crate) fn flush(&mut self, frame: usize) -> Vulkan_result<()>{
+        self.buffers.iter_mut().flatten()
+            .filter(|uniform_buffer| uniform_buffer.frame_versions[frame] != uniform_buffer.version)
+            .try_for_each(|uniform_buffer| {
+                uniform_buffer.buffers[frame].write(0, &uniform_buffer.data)?;
+                uniform_buffer.frame_versions[frame] = uniform_buffer.version;
+
+                Ok(())
+            })
+    }
+
+    //The caller must make sure the device no longer uses the buffer
+    pub(crate) fn destroy(&mut self, device: &ash::Device, allocator: &mut Memory_allocator, handle: Uniform_buffer_handle) -> Option<()>{
+        self.buffers.get_mut(handle.0 as usize)?.take()?.destroy(device, allocator);
+
+        Some(())
+    }
+
+    pub(crate) fn destroy_all(&mut self, device: &ash::Device, allocator: &mut Memory_allocator){
+        self.buffers.drain(..).flatten().for_each(|buffer| buffer.destroy(device, allocator));
+    }
+}
@@ -1,6 +1,9 @@
 //Made by Han_feng
 
 use std::ffi::{c_char, CStr};
+use crate::libs::shaders::Shader_error;
+use crate::libs::shader_store::Shader_store_error;
+use crate::libs::textures::Texture_error;
 
 cfg_if::cfg_if! {
     if #[cfg(debug_assertions)] {
@@ -90,6 +93,42 @@ impl From<ash::vk::Result> for Status_code{
     }
 }
 
+#[allow(unused_variables)]
+impl From<std::io::Error> for Status_code{
+    fn from(error: std::io::Error) -> Self {
+        save_log!(Log_level::General, Level::Error, "{:?}", error);
+
+        Status_code::Failure
+    }
+}
+
+#[allow(unused_variables)]
+impl From<Shader_error> for Status_code{
+    fn from(error: Shader_error) -> Self {
+        save_log!(Log_level::General, Level::Error, "{}", error);
+
+        Status_code::Failure
+    }
+}
+
+#[allow(unused_variables)]
+impl From<Shader_store_error> for Status_code{
+    fn from(error: Shader_store_error) -> Self {
+        save_log!(Log_level::General, Level::Error, "{}", error);
+
+        Status_code::Failure
+    }
+}
+
+#[allow(unused_variables)]
+impl From<Texture_error> for Status_code{
+    fn from(error: Texture_error) -> Self {
+        save_log!(Log_level::General, Level::Error, "{}", error);
+
+        Status_code::Failure
+    }
+}
+
 #[cfg(debug_assertions)]
 #[allow(dead_code)]
 impl Log_level{
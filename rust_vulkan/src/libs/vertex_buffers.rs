@@ -0,0 +1,339 @@
+//Made by Han_feng
+
+use std::fmt::{Display, Formatter};
+use std::mem::offset_of;
+use ash::vk;
+use serde::Deserialize;
+use crate::libs::buffers::Buffer;
+use crate::libs::memory_allocator::Memory_allocator;
+use crate::libs::shader_reflection::Interface_variable;
+use crate::libs::utils::{Status_code, Vulkan_result};
+use crate::save_log;
+
+cfg_if::cfg_if! {
+    if #[cfg(debug_assertions)] {
+        use crate::libs::utils::Log_level;
+        use log::Level;
+    }
+}
+
+//Enums
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(C)]
+pub enum Vertex_format{
+    Float = 0,
+    Vec2 = 1,
+    Vec3 = 2,
+    Vec4 = 3,
+    Int = 4,
+    Ivec2 = 5,
+    Ivec3 = 6,
+    Ivec4 = 7,
+    Uint = 8,
+    Uvec2 = 9,
+    Uvec3 = 10,
+    Uvec4 = 11,
+    //Four bytes read as a vec4 in [0, 1], e.g. a packed color
+    Unorm8x4 = 12,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Vertex_layout_error{
+    Empty,
+    Invalid_stride(u32),
+    Duplicate_location(u32),
+    Out_of_bounds{location: u32, offset: u32, size: u32},
+    Missing_location(u32),
+    Data_size{size: usize, stride: u32},
+}
+
+//Structs
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[repr(C)]
+pub struct Vertex_attribute{
+    pub location: u32,
+    pub format: Vertex_format,
+    pub offset: u32,
+}
+
+//How C hands attributes over, the format is checked before it becomes a Vertex_format
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub struct Raw_vertex_attribute{
+    pub location: u32,
+    pub format: u32,
+    pub offset: u32,
+}
+
+//One vertex buffer binding, attributes are offsets into a vertex of stride bytes
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Vertex_layout{
+    pub stride: u32,
+    pub attributes: Vec<Vertex_attribute>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Vertex_buffer_handle(pub u64);
+
+//The vertex the default pipeline draws
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[repr(C)]
+pub struct Colored_vertex{
+    pub position: [f32; 2],
+    pub color: [f32; 3],
+}
+
+pub(crate) struct Vertex_buffer{
+    pub(crate) buffer: Buffer,
+    pub(crate) layout: Vertex_layout,
+    //Instances when the buffer holds per instance data
+    pub(crate) vertex_count: u32,
+}
+
+//Destroyed buffers leave an empty slot behind so handles stay stable
+#[derive(Default)]
+pub(crate) struct Vertex_buffers{
+    buffers: Vec<Option<Vertex_buffer>>,
+}
+
+//Traits
+//Describes a vertex type, the stride is its size
+//Vertices are uploaded as raw bytes, which is why implementing it is unsafe
+/// # Safety
+/// The type has to be `#[repr(C)]` without any padding bytes, and every field has to be plain data
+/// without pointers, references or invalid bit patterns, so that all of its bytes are initialized.
+pub unsafe trait Vertex: Copy{
+    const ATTRIBUTES: &'static [Vertex_attribute];
+
+    fn get_layout() -> Vertex_layout{
+        Vertex_layout{
+            stride: size_of::<Self>() as u32,
+            attributes: Self::ATTRIBUTES.to_vec(),
+        }
+    }
+}
+
+//Functions
+//Every input the vertex shader reads has to be fed by exactly one of the layouts, extra attributes are allowed
+pub fn check_vertex_input(layouts: &[&Vertex_layout], inputs: &[Interface_variable]) -> Result<(), Vertex_layout_error>{
+    layouts.iter().try_for_each(|layout| layout.validate())?;
+
+    let mut locations = layouts.iter().flat_map(|layout| layout.attributes.iter().map(|attribute| attribute.location)).collect::<Vec<_>>();
+    locations.sort_unstable();
+    if let Some(pair) = locations.windows(2).find(|pair| pair[0] == pair[1]) {
+        return Err(Vertex_layout_error::Duplicate_location(pair[0]))
+    }
+
+    match inputs.iter().find(|input| locations.binary_search(&input.location).is_err()) {
+        Some(input) => Err(Vertex_layout_error::Missing_location(input.location)),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn get_vertex_bytes<V: Vertex>(vertices: &[V]) -> &[u8]{
+    //Vertex guarantees there are no padding bytes
+    unsafe { std::slice::from_raw_parts(vertices.as_ptr() as *const u8, size_of_val(vertices)) }
+}
+
+//Impls
+impl Vertex_format{
+    pub fn get_size(&self) -> u32{
+        match self {
+            Self::Float | Self::Int | Self::Uint | Self::Unorm8x4 => 4,
+            Self::Vec2 | Self::Ivec2 | Self::Uvec2 => 8,
+            Self::Vec3 | Self::Ivec3 | Self::Uvec3 => 12,
+            Self::Vec4 | Self::Ivec4 | Self::Uvec4 => 16,
+        }
+    }
+}
+
+impl TryFrom<u32> for Vertex_format{
+    type Error = Status_code;
+
+    #[allow(unused_variables)]
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Self::Float,
+            1 => Self::Vec2,
+            2 => Self::Vec3,
+            3 => Self::Vec4,
+            4 => Self::Int,
+            5 => Self::Ivec2,
+            6 => Self::Ivec3,
+            7 => Self::Ivec4,
+            8 => Self::Uint,
+            9 => Self::Uvec2,
+            10 => Self::Uvec3,
+            11 => Self::Uvec4,
+            12 => Self::Unorm8x4,
+            _ => {
+                save_log!(Log_level::General, Level::Error, "Unknown vertex format {}", value);
+                return Err(Status_code::Failure)
+            }
+        })
+    }
+}
+
+impl TryFrom<Raw_vertex_attribute> for Vertex_attribute{
+    type Error = Status_code;
+
+    fn try_from(attribute: Raw_vertex_attribute) -> Result<Self, Self::Error> {
+        Ok(Vertex_attribute{
+            location: attribute.location,
+            format: attribute.format.try_into()?,
+            offset: attribute.offset,
+        })
+    }
+}
+
+impl From<Vertex_format> for vk::Format{
+    fn from(format: Vertex_format) -> Self {
+        match format {
+            Vertex_format::Float => vk::Format::R32_SFLOAT,
+            Vertex_format::Vec2 => vk::Format::R32G32_SFLOAT,
+            Vertex_format::Vec3 => vk::Format::R32G32B32_SFLOAT,
+            Vertex_format::Vec4 => vk::Format::R32G32B32A32_SFLOAT,
+            Vertex_format::Int => vk::Format::R32_SINT,
+            Vertex_format::Ivec2 => vk::Format::R32G32_SINT,
+            Vertex_format::Ivec3 => vk::Format::R32G32B32_SINT,
+            Vertex_format::Ivec4 => vk::Format::R32G32B32A32_SINT,
+            Vertex_format::Uint => vk::Format::R32_UINT,
+            Vertex_format::Uvec2 => vk::Format::R32G32_UINT,
+            Vertex_format::Uvec3 => vk::Format::R32G32B32_UINT,
+            Vertex_format::Uvec4 => vk::Format::R32G32B32A32_UINT,
+            Vertex_format::Unorm8x4 => vk::Format::R8G8B8A8_UNORM,
+        }
+    }
+}
+
+impl Vertex_layout{
+    pub fn validate(&self) -> Result<(), Vertex_layout_error>{
+        if self.attributes.is_empty() {
+            return Err(Vertex_layout_error::Empty)
+        }
+        if self.stride == 0 {
+            return Err(Vertex_layout_error::Invalid_stride(self.stride))
+        }
+
+        for (index, attribute) in self.attributes.iter().enumerate() {
+            if self.attributes[..index].iter().any(|other| other.location == attribute.location) {
+                return Err(Vertex_layout_error::Duplicate_location(attribute.location))
+            }
+
+            let size = attribute.format.get_size();
+            if attribute.offset.checked_add(size).is_none_or(|end| end > self.stride) {
+                return Err(Vertex_layout_error::Out_of_bounds{location: attribute.location, offset: attribute.offset, size})
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn check(&self, inputs: &[Interface_variable]) -> Result<(), Vertex_layout_error>{
+        check_vertex_input(&[self], inputs)
+    }
+
+    pub(crate) fn get_binding_description(&self, binding: u32, input_rate: vk::VertexInputRate) -> vk::VertexInputBindingDescription{
+        vk::VertexInputBindingDescription::default()
+            .binding(binding)
+            .stride(self.stride)
+            .input_rate(input_rate)
+    }
+
+    pub(crate) fn get_attribute_descriptions(&self, binding: u32) -> Vec<vk::VertexInputAttributeDescription>{
+        self.attributes.iter().map(|attribute| {
+            vk::VertexInputAttributeDescription::default()
+                .location(attribute.location)
+                .binding(binding)
+                .format(attribute.format.into())
+                .offset(attribute.offset)
+        }).collect()
+    }
+
+    fn get_vertex_count(&self, data: &[u8]) -> Result<u32, Vertex_layout_error>{
+        let stride = self.stride as usize;
+        if data.is_empty() || data.len() % stride != 0 {
+            return Err(Vertex_layout_error::Data_size{size: data.len(), stride: self.stride})
+        }
+
+        Ok((data.len() / stride) as u32)
+    }
+}
+
+impl Vertex_buffer_handle{
+    pub const DEFAULT: Self = Vertex_buffer_handle(0);
+    //Marks a draw without per instance data
+    pub const NONE: Self = Vertex_buffer_handle(u64::MAX);
+}
+
+impl Colored_vertex{
+    //The triangle drawn until something else is bound
+    pub const TRIANGLE: [Self; 3] = [
+        Colored_vertex{position: [0.0, -0.5], color: [1.0, 0.0, 0.0]},
+        Colored_vertex{position: [0.5, 0.5], color: [0.0, 1.0, 0.0]},
+        Colored_vertex{position: [-0.5, 0.5], color: [0.0, 0.0, 1.0]},
+    ];
+}
+
+//Five f32 in a row, 4 byte aligned, so there is no padding
+unsafe impl Vertex for Colored_vertex{
+    const ATTRIBUTES: &'static [Vertex_attribute] = &[
+        Vertex_attribute{location: 0, format: Vertex_format::Vec2, offset: offset_of!(Colored_vertex, position) as u32},
+        Vertex_attribute{location: 1, format: Vertex_format::Vec3, offset: offset_of!(Colored_vertex, color) as u32},
+    ];
+}
+
+impl Vertex_buffers{
+    #[allow(unused_variables)]
+    pub(crate) fn create(&mut self, device: &ash::Device, allocator: &mut Memory_allocator, pool: vk::CommandPool, queue: vk::Queue, layout: Vertex_layout, data: &[u8]) -> Vulkan_result<Vertex_buffer_handle>{
+        let vertex_count = layout.validate().and_then(|_| layout.get_vertex_count(data)).map_err(|error| {
+            save_log!(Log_level::General, Level::Error, "Failed to create vertex buffer: {}", error);
+
+            Status_code::Failure
+        })?;
+
+        let buffer = Buffer::new_device_local(device, allocator, pool, queue, data, vk::BufferUsageFlags::VERTEX_BUFFER)?;
+        let handle = Vertex_buffer_handle(self.buffers.len() as u64);
+        self.buffers.push(Some(Vertex_buffer{
+            buffer, layout, vertex_count
+        }));
+
+        save_log!(Log_level::General, Level::Info, "Created vertex buffer {} with {} vertices", handle.0, vertex_count);
+
+        Ok(handle)
+    }
+
+    pub(crate) fn get(&self, handle: Vertex_buffer_handle) -> Option<&Vertex_buffer>{
+        self.buffers.get(handle.0 as usize)?.as_ref()
+    }
+
+    //The caller must make sure the device no longer uses the buffer
+    pub(crate) fn destroy(&mut self, device: &ash::Device, allocator: &mut Memory_allocator, handle: Vertex_buffer_handle) -> Option<()>{
+        self.buffers.get_mut(handle.0 as usize)?.take()?.buffer.destroy(device, allocator);
+
+        Some(())
+    }
+
+    pub(crate) fn destroy_all(&mut self, device: &ash::Device, allocator: &mut Memory_allocator){
+        self.buffers.drain(..).flatten().for_each(|buffer| buffer.buffer.destroy(device, allocator));
+    }
+}
+
+impl Display for Vertex_layout_error{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "vertex layout has no attributes"),
+            Self::Invalid_stride(stride) => write!(f, "invalid vertex stride {}", stride),
+            Self::Duplicate_location(location) => write!(f, "location {} is declared more than once", location),
+            Self::Out_of_bounds{location, offset, size} => write!(f, "attribute at location {} spans {}..{}, past the stride", location, offset, *offset as u64 + *size as u64),
+            Self::Missing_location(location) => write!(f, "vertex shader input at location {} is not fed by the layout", location),
+            Self::Data_size{size, stride} => write!(f, "{} bytes are not a whole number of {} byte vertices", size, stride),
+        }
+    }
+}
+
+impl std::error::Error for Vertex_layout_error{}
@@ -2,11 +2,24 @@
 
 use std::collections::HashSet;
 use crate::libs::utils::{C_char_extension, Status_code, Vulkan_result};
+use crate::libs::pipeline_cache::Pipeline_cache;
+use crate::libs::pipeline_manifest::Pipeline_manifest;
+use crate::libs::shaders::Shader_library;
+use crate::libs::shader_store::Shader_hash;
+use crate::libs::pipelines::{create_render_pass, Graphics_pipelines, Pipeline_handle, Pipeline_report, Pipeline_status};
+use crate::libs::vertex_buffers::{get_vertex_bytes, Colored_vertex, Vertex, Vertex_buffer_handle, Vertex_buffers, Vertex_layout};
+use crate::libs::index_buffers::{get_index_bytes, Index, Index_buffer_handle, Index_buffers, Index_type};
+use crate::libs::draw_list::{Draw, Draw_command};
+use crate::libs::memory_allocator::{Memory_allocator, Memory_report};
+use crate::libs::uniform_buffers::{Uniform_buffer_handle, Uniform_buffers};
+use crate::libs::descriptors::{Descriptor_set_handle, Descriptor_sets};
+use crate::libs::textures::{decode_image, decode_image_file, Image_data, Texture_handle, Textures};
 use ash::vk;
 use raw_window_handle::{RawDisplayHandle, RawWindowHandle};
-use std::ffi::{c_char, c_void, OsStr};
-use std::ops::Index;
+use std::ffi::{c_char, c_void, CStr};
+use std::path::{Path, PathBuf};
 use std::ptr::NonNull;
+use std::thread::JoinHandle;
 use crate::save_log;
 
 cfg_if::cfg_if! {
@@ -17,40 +30,83 @@ cfg_if::cfg_if! {
     }
 }
 
+#[cfg(all(debug_assertions, feature = "hot_reload"))]
+use crate::libs::hot_reload::Shader_watcher;
+
 //Consts
+//Shaders registered at runtime are checked against what this version consumes
+pub(crate) const API_VERSION: u32 = vk::API_VERSION_1_0;
+
 const DEVICE_EXTENSIONS: [*const c_char; 1] = [
     ash::khr::swapchain::NAME.as_ptr()
 ];
 
 const IMAGE_EXTENSION: u32 = 2;
 
-const VERTEX_SHADER: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/shaders/vertex_shader.spv"));
-const FRAGMENT_SHADER: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/shaders/fragment_shader.spv"));
+const PREFERRED_FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
 
 const MAX_FRAMES_IN_FLIGHT: u32 = 2;
 
 //Structs
-#[allow(unused)]
+#[derive(Clone, Default)]
+pub struct Vulkan_config{
+    pub vulkan_path: Option<PathBuf>,
+    pub pipeline_cache_path: Option<PathBuf>,
+    pub pipeline_manifest_path: Option<PathBuf>,
+    pub shader_store_path: Option<PathBuf>,
+}
+
 pub struct Vulkan_application {
+    context: Vulkan_context,
+    presentation: Option<Presentation>,
+
+    //Runtime attributes
+    current_frame: usize,
+}
+
+pub struct Vulkan_prewarm(JoinHandle<Vulkan_result<Vulkan_context>>);
+
+//Everything that does not depend on the surface, so it can be built before the window exists
+#[allow(unused)]
+struct Vulkan_context{
     //Vulkan attributes
     vulkan_entry: ash::Entry,
     instance: ash::Instance,
-    window: Window,
-    surface: Surface,
     physical_device: vk::PhysicalDevice,
+    properties: vk::PhysicalDeviceProperties,
     indices: Queue_family_indices,
     device: ash::Device,
-    queues: Queues,
-    swap_chain: Swap_chain,
+    graphics_queue: vk::Queue,
+    render_pass: vk::RenderPass,
+    pipeline_cache: Pipeline_cache,
     graphics_pipelines: Graphics_pipelines,
     command_context: Command_context,
-    sync_objects: Sync_objects,
+    shader_library: Shader_library,
+    allocator: Memory_allocator,
+    vertex_buffers: Vertex_buffers,
+    index_buffers: Index_buffers,
+    uniform_buffers: Uniform_buffers,
+    descriptor_sets: Descriptor_sets,
+    textures: Textures,
 
     #[cfg(debug_assertions)]
     _debug_messenger: Debug_messenger,
+    //None when the shader directory could not be watched
+    #[cfg(all(debug_assertions, feature = "hot_reload"))]
+    shader_watcher: Option<Shader_watcher>,
 
-    //Runtime attributes
-    current_frame: usize,
+    //Attributes
+    presentable: bool,
+    render_format: vk::Format,
+}
+
+struct Presentation{
+    window: Window,
+    surface: Surface,
+    indices: Queue_family_indices,
+    present_queue: vk::Queue,
+    swap_chain: Swap_chain,
+    sync_objects: Sync_objects,
 }
 
 #[cfg(debug_assertions)]
@@ -81,12 +137,6 @@ struct Queue_family_indices{
     elements: Option<Vec<Option<u32>>>,
 }
 
-#[derive(Copy, Clone)]
-struct Queues{
-    graphics_queue: vk::Queue,
-    present_queue: vk::Queue,
-}
-
 struct Swap_chain_supports{
     capabilities: vk::SurfaceCapabilitiesKHR,
     formats: Vec<vk::SurfaceFormatKHR>,
@@ -96,7 +146,6 @@ struct Swap_chain_supports{
 struct Swap_chain{
     device: ash::khr::swapchain::Device,
     swap_chain: vk::SwapchainKHR,
-    render_pass: vk::RenderPass,
     images: Vec<vk::Image>,
     image_views: Vec<vk::ImageView>,
     frame_buffers: Vec<vk::Framebuffer>,
@@ -106,11 +155,6 @@ struct Swap_chain{
     extent: vk::Extent2D,
 }
 
-struct Graphics_pipelines {
-    layout: vk::PipelineLayout,
-    pipelines: Vec<vk::Pipeline>,
-}
-
 struct Command_context{
     pool: vk::CommandPool,
     draw_buffers: Vec<vk::CommandBuffer>,
@@ -124,77 +168,113 @@ struct Sync_objects{
 
 //Impls
 impl Vulkan_application {
-    pub fn new(window: Window, vulkan_path: Option<impl AsRef<OsStr>>) -> Vulkan_result<Self> {
-        let vulkan_entry = Self::get_vulkan_entry(vulkan_path)?;
-        let instance = Self::get_instance(&vulkan_entry, window.display_handle)?;
+    pub fn new(window: Window, config: Vulkan_config) -> Vulkan_result<Self> {
+        let mut application = Self::new_headless(Some(window.display_handle), config)?;
+        application.attach_surface(window)?;
 
-        #[cfg(debug_assertions)]
-        let _debug_messenger = Debug_messenger::new(&vulkan_entry, &instance)?;
+        Ok(application)
+    }
 
-        let surface = Surface::new(&vulkan_entry, &instance, window)?;
+    //Without a display handle the instance is created without surface extensions and can never present
+    pub fn new_headless(display_handle: Option<RawDisplayHandle>, config: Vulkan_config) -> Vulkan_result<Self> {
+        let context = Vulkan_context::new(Self::get_surface_extensions(display_handle)?, config)?;
 
-        let (physical_device, indices) = Self::get_physical_device_and_indices(&instance, &surface)?;
+        Ok(Self::from_context(context))
+    }
 
-        let (device, queues) = Self::get_device_and_queues(&instance, physical_device, &indices)?;
+    pub fn prewarm(display_handle: Option<RawDisplayHandle>, config: Vulkan_config) -> Vulkan_result<Vulkan_prewarm> {
+        let surface_extensions = Self::get_surface_extensions(display_handle)?;
 
-        let swap_chain = Swap_chain::new(&instance, &device, physical_device, &indices, &surface, &window)?;
+        let handle = std::thread::Builder::new()
+            .name("vulkan_prewarm".to_string())
+            .spawn(move || Vulkan_context::new(surface_extensions, config))?;
 
-        let graphics_pipelines = Graphics_pipelines::new(&device, swap_chain.render_pass)?;
+        save_log!(Log_level::General, Level::Info, "Started vulkan prewarm thread");
 
-        let command_context = Command_context::new(&device, &indices)?;
+        Ok(Vulkan_prewarm(handle))
+    }
 
-        let sync_objects = Sync_objects::new(&device, swap_chain.images.len())?;
+    pub fn attach_surface(&mut self, window: Window) -> Vulkan_result<()> {
+        if !self.context.presentable {
+            save_log!(Log_level::General, Level::Error, "Vulkan application was created without surface extensions");
+            return Err(Status_code::Failure)
+        }
 
-        save_log!(Log_level::General, Level::Info, "Successfully created vulkan application");
-        
-        Ok(Vulkan_application{
-            vulkan_entry, instance, window, surface, physical_device,
-            indices, device, queues, swap_chain, graphics_pipelines,
-            command_context, sync_objects,
+        unsafe { self.context.device.device_wait_idle()? };
+        if let Some(presentation) = self.presentation.take() {
+            presentation.destroy(&self.context.device);
+        }
 
-            #[cfg(debug_assertions)]
-            _debug_messenger,
+        let presentation = Presentation::new(&mut self.context, window)?;
+        self.presentation = Some(presentation);
+        self.current_frame = 0;
 
-            current_frame: 0,
-        })
+        save_log!(Log_level::General, Level::Info, "Successfully attached surface");
+
+        Ok(())
     }
 
-    pub fn draw_frame(&mut self) -> Vulkan_result<()>{
+    //Draws run in order, those whose pipeline is still compiling are skipped
+    //Uniform buffer updates made before the call are seen by this frame
+    //Push constants hold the bytes every draw takes its push constants from
+    #[allow(unused_variables)]
+    pub fn draw_frame(&mut self, draw_commands: &[Draw_command], push_constants: &[u8]) -> Vulkan_result<()>{
+        #[cfg(all(debug_assertions, feature = "hot_reload"))]
+        self.context.reload_shaders()?;
+
+        let Some(presentation) = self.presentation.as_mut() else {
+            save_log!(Log_level::General, Level::Error, "Failed to draw frame: no surface attached");
+            return Err(Status_code::Failure)
+        };
+        let sync_objects = &presentation.sync_objects;
+
+        //The frame's uniform buffers are only free to write once its previous submission is done
+        unsafe { self.context.device.wait_for_fences(&sync_objects.in_flight_fences[self.current_frame..self.current_frame+1], true, u64::MAX)? };
+        self.context.uniform_buffers.flush(self.current_frame)?;
+
+        let context = &self.context;
+        let draws = draw_commands.iter().enumerate().filter_map(|(index, command)| {
+            command.resolve(&context.graphics_pipelines, &context.vertex_buffers, &context.index_buffers, &context.descriptor_sets, push_constants, self.current_frame).map_err(|error| {
+                save_log!(Log_level::General, Level::Error, "Failed to draw frame: draw {}: {}", index, error);
+
+                Status_code::Failure
+            }).transpose()
+        }).collect::<Vulkan_result<Vec<_>>>()?;
+
         unsafe{
-            self.device.wait_for_fences(&self.sync_objects.in_flight_fences[self.current_frame..self.current_frame+1], true, u64::MAX)?;
-            self.device.reset_fences(&self.sync_objects.in_flight_fences[self.current_frame..self.current_frame+1])?;
+            context.device.reset_fences(&sync_objects.in_flight_fences[self.current_frame..self.current_frame+1])?;
 
-            let (image_index, suboptimal) = match self.swap_chain.device.acquire_next_image(self.swap_chain.swap_chain, u64::MAX, self.sync_objects.image_available_semaphores[self.current_frame], vk::Fence::null()){
+            let (image_index, suboptimal) = match presentation.swap_chain.device.acquire_next_image(presentation.swap_chain.swap_chain, u64::MAX, sync_objects.image_available_semaphores[self.current_frame], vk::Fence::null()){
                 Ok((index, suboptimal)) => (index as usize, suboptimal),
                 Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
-                    return Ok(self.swap_chain.recreate(&self.device, self.physical_device, &self.indices, &self.surface, &self.window)?);
+                    return presentation.recreate_swap_chain(context);
                 },
                 Err(error) => Err(error)?,
             };
 
             let command_buffers = [
-                self.command_context.get_draw_buffer(&self.device, self.current_frame, image_index, &self.swap_chain, &self.graphics_pipelines)?
+                context.command_context.get_draw_buffer(&context.device, self.current_frame, image_index, &presentation.swap_chain, context.render_pass, &draws)?
             ];
             let submit_infos = [
                 vk::SubmitInfo::default()
-                    .wait_semaphores(&self.sync_objects.image_available_semaphores[self.current_frame..self.current_frame+1])
+                    .wait_semaphores(&sync_objects.image_available_semaphores[self.current_frame..self.current_frame+1])
                     .wait_dst_stage_mask(&[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT])
                     .command_buffers(&command_buffers)
-                    .signal_semaphores(&self.sync_objects.render_finished_semaphores[image_index..image_index+1])
+                    .signal_semaphores(&sync_objects.render_finished_semaphores[image_index..image_index+1])
             ];
 
-            self.device.queue_submit(self.queues.graphics_queue, &submit_infos, self.sync_objects.in_flight_fences[self.current_frame])?;
+            context.device.queue_submit(context.graphics_queue, &submit_infos, sync_objects.in_flight_fences[self.current_frame])?;
 
-            let swap_chains = [self.swap_chain.swap_chain];
+            let swap_chains = [presentation.swap_chain.swap_chain];
             let image_indices = [image_index as u32];
             let present_info = vk::PresentInfoKHR::default()
-                .wait_semaphores(&self.sync_objects.render_finished_semaphores[image_index..image_index+1])
+                .wait_semaphores(&sync_objects.render_finished_semaphores[image_index..image_index+1])
                 .swapchains(&swap_chains)
                 .image_indices(&image_indices);
 
-            match self.swap_chain.device.queue_present(self.queues.present_queue, &present_info){
+            match presentation.swap_chain.device.queue_present(presentation.present_queue, &present_info){
                 Ok(false) if !suboptimal => (),
-                Ok(_) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => self.swap_chain.recreate(&self.device, self.physical_device, &self.indices, &self.surface, &self.window)?,
+                Ok(_) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => presentation.recreate_swap_chain(context)?,
                 Err(error) => Err(error)?,
             };
         }
@@ -203,7 +283,438 @@ impl Vulkan_application {
         Ok(())
     }
 
-    fn get_vulkan_entry(vulkan_path: Option<impl AsRef<OsStr>>) -> Vulkan_result<ash::Entry> {
+    pub fn save_pipeline_cache(&self) -> Vulkan_result<()>{
+        self.context.pipeline_cache.save(&self.context.device, &self.context.graphics_pipelines.get_shader_hashes())
+    }
+
+    pub fn get_pipeline_status(&self, handle: Pipeline_handle) -> Option<Pipeline_status>{
+        self.context.graphics_pipelines.get_status(handle)
+    }
+
+    pub fn wait_pipeline(&self, handle: Pipeline_handle) -> Option<Pipeline_status>{
+        self.context.graphics_pipelines.wait(handle)
+    }
+
+    pub fn get_pipeline_handle(&self, name: &str) -> Option<Pipeline_handle>{
+        self.context.graphics_pipelines.find(name)
+    }
+
+    pub fn load_pipeline_manifest(&mut self, path: impl AsRef<Path>) -> Vulkan_result<Vec<Pipeline_handle>>{
+        self.context.load_pipeline_manifest(path)
+    }
+
+    //Registered modules are picked up by pipelines created afterwards, e.g. from a manifest
+    pub fn register_shader(&mut self, name: &str, data: &[u8]) -> Vulkan_result<()>{
+        self.context.shader_library.register(name, data)?;
+
+        save_log!(Log_level::General, Level::Info, "Registered shader {}", name);
+
+        Ok(())
+    }
+
+    pub fn register_shader_file(&mut self, name: &str, path: impl AsRef<Path>) -> Vulkan_result<()>{
+        self.context.shader_library.register_file(name, path)?;
+
+        save_log!(Log_level::General, Level::Info, "Registered shader {}", name);
+
+        Ok(())
+    }
+
+    pub fn open_shader_store(&mut self, path: impl AsRef<Path>) -> Vulkan_result<()>{
+        self.context.shader_library.open_store(path)?;
+
+        Ok(())
+    }
+
+    //Manifests refer to the stored module as sha256:<hash>
+    pub fn store_shader(&mut self, data: &[u8]) -> Vulkan_result<Shader_hash>{
+        let hash = self.context.shader_library.store(data)?;
+
+        save_log!(Log_level::General, Level::Info, "Stored shader {}", hash);
+
+        Ok(hash)
+    }
+
+    pub fn get_memory_report(&self) -> Memory_report{
+        self.context.allocator.get_report()
+    }
+
+    pub fn create_vertex_buffer<V: Vertex>(&mut self, vertices: &[V]) -> Vulkan_result<Vertex_buffer_handle>{
+        self.context.create_vertex_buffer(V::get_layout(), get_vertex_bytes(vertices))
+    }
+
+    //For vertices that have no Rust type, data holds whole vertices of layout.stride bytes
+    pub fn create_vertex_buffer_from_bytes(&mut self, layout: Vertex_layout, data: &[u8]) -> Vulkan_result<Vertex_buffer_handle>{
+        self.context.create_vertex_buffer(layout, data)
+    }
+
+    //Waits for the device, the buffer may still be read by a frame in flight
+    pub fn destroy_vertex_buffer(&mut self, handle: Vertex_buffer_handle) -> Vulkan_result<()>{
+        unsafe { self.context.device.device_wait_idle()? };
+
+        self.context.vertex_buffers.destroy(&self.context.device, &mut self.context.allocator, handle).ok_or_else(|| {
+            save_log!(Log_level::General, Level::Error, "Unknown vertex buffer {}", handle.0);
+
+            Status_code::Failure
+        })?;
+
+        Ok(())
+    }
+
+    pub fn create_index_buffer<I: Index>(&mut self, indices: &[I]) -> Vulkan_result<Index_buffer_handle>{
+        self.context.create_index_buffer(I::TYPE, get_index_bytes(indices))
+    }
+
+    pub fn create_index_buffer_from_bytes(&mut self, index_type: Index_type, data: &[u8]) -> Vulkan_result<Index_buffer_handle>{
+        self.context.create_index_buffer(index_type, data)
+    }
+
+    //Waits for the device, the buffer may still be read by a frame in flight
+    pub fn destroy_index_buffer(&mut self, handle: Index_buffer_handle) -> Vulkan_result<()>{
+        unsafe { self.context.device.device_wait_idle()? };
+
+        self.context.index_buffers.destroy(&self.context.device, &mut self.context.allocator, handle).ok_or_else(|| {
+            save_log!(Log_level::General, Level::Error, "Unknown index buffer {}", handle.0);
+
+            Status_code::Failure
+        })
+    }
+
+    //Holds size bytes, kept once per frame in flight
+    #[allow(unused_variables)]
+    pub fn create_uniform_buffer(&mut self, size: u64) -> Vulkan_result<Uniform_buffer_handle>{
+        let limit = self.context.properties.limits.max_uniform_buffer_range as u64;
+        if size == 0 || size > limit {
+            save_log!(Log_level::General, Level::Error, "Failed to create uniform buffer: {} bytes, the device allows 1 to {}", size, limit);
+            return Err(Status_code::Failure)
+        }
+
+        let context = &mut self.context;
+        context.uniform_buffers.create(&context.device, &mut context.allocator, size, MAX_FRAMES_IN_FLIGHT as usize)
+    }
+
+    //Replaces the whole block, frames still in flight keep the data they were submitted with
+    pub fn update_uniform_buffer(&mut self, handle: Uniform_buffer_handle, data: &[u8]) -> Vulkan_result<()>{
+        self.context.uniform_buffers.update(handle, data)
+    }
+
+    //Waits for the device, the buffer may still be read by a frame in flight
+    //Descriptor sets still pointing at it must not be drawn with anymore
+    pub fn destroy_uniform_buffer(&mut self, handle: Uniform_buffer_handle) -> Vulkan_result<()>{
+        unsafe { self.context.device.device_wait_idle()? };
+
+        self.context.uniform_buffers.destroy(&self.context.device, &mut self.context.allocator, handle).ok_or_else(|| {
+            save_log!(Log_level::General, Level::Error, "Unknown uniform buffer {}", handle.0);
+
+            Status_code::Failure
+        })
+    }
+
+    //Laid out like set of the pipeline, it can be drawn with any pipeline declaring the same bindings there
+    #[allow(unused_variables)]
+    pub fn create_descriptor_set(&mut self, pipeline: Pipeline_handle, set: u32) -> Vulkan_result<Descriptor_set_handle>{
+        let context = &mut self.context;
+        let Some(interface) = context.graphics_pipelines.get_interface(pipeline) else {
+            save_log!(Log_level::General, Level::Error, "Failed to create descriptor set: unknown pipeline {}", pipeline.0);
+            return Err(Status_code::Failure)
+        };
+
+        let bindings = interface.get_set_bindings(set);
+        if bindings.is_empty() {
+            save_log!(Log_level::General, Level::Error, "Failed to create descriptor set: pipeline {} has no bindings in set {}", pipeline.0, set);
+            return Err(Status_code::Failure)
+        }
+
+        //Writes fill a single element, so an arrayed binding could never be fully written
+        if let Some(binding) = bindings.iter().find(|binding| binding.count != 1) {
+            save_log!(Log_level::General, Level::Error, "Failed to create descriptor set: binding {} of set {} in pipeline {} is an array of {}, arrays are not supported", binding.binding, set, pipeline.0, binding.count);
+            return Err(Status_code::Failure)
+        }
+
+        context.descriptor_sets.create(&context.device, set, interface.set_layouts[set as usize], bindings, MAX_FRAMES_IN_FLIGHT as usize)
+    }
+
+    //Waits for the device, the set may still be used by a frame in flight
+    #[allow(unused_variables)]
+    pub fn bind_uniform_buffer(&mut self, descriptor_set: Descriptor_set_handle, binding: u32, uniform_buffer: Uniform_buffer_handle) -> Vulkan_result<()>{
+        let context = &mut self.context;
+        let Some(uniform_buffer) = context.uniform_buffers.get(uniform_buffer) else {
+            save_log!(Log_level::General, Level::Error, "Unknown uniform buffer {}", uniform_buffer.0);
+            return Err(Status_code::Failure)
+        };
+
+        unsafe { context.device.device_wait_idle()? };
+
+        context.descriptor_sets.write_uniform_buffer(&context.device, descriptor_set, binding, uniform_buffer)
+    }
+
+    //Waits for the device, the set may still be used by a frame in flight
+    pub fn destroy_descriptor_set(&mut self, handle: Descriptor_set_handle) -> Vulkan_result<()>{
+        unsafe { self.context.device.device_wait_idle()? };
+
+        self.context.descriptor_sets.destroy(&self.context.device, handle).ok_or_else(|| {
+            save_log!(Log_level::General, Level::Error, "Unknown descriptor set {}", handle.0);
+
+            Status_code::Failure
+        })
+    }
+
+    //JPEG or PNG data
+    pub fn load_texture(&mut self, data: &[u8]) -> Vulkan_result<Texture_handle>{
+        self.context.create_texture(&decode_image(data)?)
+    }
+
+    pub fn load_texture_file(&mut self, path: impl AsRef<Path>) -> Vulkan_result<Texture_handle>{
+        self.context.create_texture(&decode_image_file(path)?)
+    }
+
+    //For pixels decoded elsewhere
+    pub fn create_texture(&mut self, image_data: &Image_data) -> Vulkan_result<Texture_handle>{
+        self.context.create_texture(image_data)
+    }
+
+    //Width and height in pixels
+    pub fn get_texture_size(&self, handle: Texture_handle) -> Option<(u32, u32)>{
+        self.context.textures.get(handle).map(|texture| (texture.width, texture.height))
+    }
+
+    //Waits for the device, the set may still be used by a frame in flight
+    #[allow(unused_variables)]
+    pub fn bind_texture(&mut self, descriptor_set: Descriptor_set_handle, binding: u32, texture: Texture_handle) -> Vulkan_result<()>{
+        let context = &mut self.context;
+        let Some(view) = context.textures.get(texture).map(|texture| texture.view) else {
+            save_log!(Log_level::General, Level::Error, "Unknown texture {}", texture.0);
+            return Err(Status_code::Failure)
+        };
+
+        unsafe { context.device.device_wait_idle()? };
+
+        context.descriptor_sets.write_texture(&context.device, descriptor_set, binding, view, context.textures.get_sampler())
+    }
+
+    //Waits for the device, the texture may still be sampled by a frame in flight
+    //Descriptor sets still pointing at it must not be drawn with anymore
+    pub fn destroy_texture(&mut self, handle: Texture_handle) -> Vulkan_result<()>{
+        unsafe { self.context.device.device_wait_idle()? };
+
+        self.context.textures.destroy(&self.context.device, &mut self.context.allocator, handle).ok_or_else(|| {
+            save_log!(Log_level::General, Level::Error, "Unknown texture {}", handle.0);
+
+            Status_code::Failure
+        })
+    }
+
+    //Blocks until no pipeline is pending, failed ones are logged
+    #[allow(unused_variables)]
+    pub fn wait_pipelines(&self) -> Vec<Pipeline_report>{
+        let reports = self.context.graphics_pipelines.wait_all();
+
+        reports.iter().filter(|report| report.status == Pipeline_status::Failed).for_each(|report| {
+            save_log!(Log_level::General, Level::Warn, "Graphics pipeline {} failed to compile", report.name);
+        });
+
+        reports
+    }
+
+    fn from_context(context: Vulkan_context) -> Self {
+        save_log!(Log_level::General, Level::Info, "Successfully created vulkan application");
+
+        Vulkan_application{
+            context,
+            presentation: None,
+            current_frame: 0,
+        }
+    }
+
+    fn get_surface_extensions(display_handle: Option<RawDisplayHandle>) -> Vulkan_result<Vec<&'static CStr>> {
+        Ok(match display_handle {
+            Some(display_handle) => ash_window::enumerate_required_extensions(display_handle)?
+                .iter()
+                .map(|&extension| unsafe { CStr::from_ptr(extension) })
+                .collect(),
+            None => vec![],
+        })
+    }
+}
+
+impl Drop for Vulkan_application {
+    fn drop(&mut self) {
+        unsafe {
+            self.context.device.device_wait_idle().unwrap();
+
+            if let Some(presentation) = self.presentation.take() {
+                presentation.destroy(&self.context.device);
+            }
+        }
+
+        //The default triangle belongs to the application, whatever the allocator still holds without it was never destroyed
+        let context = &mut self.context;
+        context.vertex_buffers.destroy(&context.device, &mut context.allocator, Vertex_buffer_handle::DEFAULT);
+        if !context.allocator.check_teardown() {
+            save_log!(Log_level::General, Level::Warn, "Releasing the resources still alive at teardown");
+        }
+        context.release_resources();
+    }
+}
+
+impl Vulkan_prewarm {
+    pub fn is_finished(&self) -> bool {
+        self.0.is_finished()
+    }
+
+    pub fn wait(self) -> Vulkan_result<Vulkan_application> {
+        let context = self.0.join().map_err(|_| {
+            save_log!(Log_level::General, Level::Error, "Vulkan prewarm thread panicked");
+
+            Status_code::Failure
+        })??;
+
+        Ok(Vulkan_application::from_context(context))
+    }
+}
+
+impl Vulkan_context {
+    fn new(surface_extensions: Vec<&'static CStr>, config: Vulkan_config) -> Vulkan_result<Self> {
+        let presentable = !surface_extensions.is_empty();
+
+        let vulkan_entry = Self::get_vulkan_entry(config.vulkan_path)?;
+        let instance = Self::get_instance(&vulkan_entry, &surface_extensions)?;
+
+        #[cfg(debug_assertions)]
+        let _debug_messenger = Debug_messenger::new(&vulkan_entry, &instance)?;
+
+        let (physical_device, indices) = Self::get_physical_device_and_indices(&instance, presentable)?;
+        let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+        let memory_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+
+        let device = Self::get_device(&instance, physical_device, presentable)?;
+        let graphics_queue = unsafe { device.get_device_queue(indices.graphics_family.unwrap(), 0) };
+
+        //The surface format is unknown until a surface is attached, so start from the one we prefer
+        let render_format = PREFERRED_FORMAT;
+        let render_pass = create_render_pass(&device, render_format)?;
+
+        let pipeline_cache = Pipeline_cache::new(&device, &properties, config.pipeline_cache_path)?;
+
+        let graphics_pipelines = Graphics_pipelines::new(&device, render_pass, &pipeline_cache, properties.limits.max_push_constants_size)?;
+
+        let command_context = Command_context::new(&device, &indices)?;
+
+        let mut context = Vulkan_context{
+            vulkan_entry, instance, physical_device, properties, indices,
+            device, graphics_queue, render_pass, pipeline_cache,
+            graphics_pipelines, command_context,
+            shader_library: Shader_library::default(),
+            allocator: Memory_allocator::new(memory_properties),
+            vertex_buffers: Vertex_buffers::default(),
+            index_buffers: Index_buffers::default(),
+            uniform_buffers: Uniform_buffers::default(),
+            descriptor_sets: Descriptor_sets::default(),
+            textures: Textures::default(),
+
+            #[cfg(debug_assertions)]
+            _debug_messenger,
+            #[cfg(all(debug_assertions, feature = "hot_reload"))]
+            shader_watcher: Shader_watcher::new().ok(),
+
+            presentable, render_format,
+        };
+
+        //Lands on Vertex_buffer_handle::DEFAULT
+        context.create_vertex_buffer(Colored_vertex::get_layout(), get_vertex_bytes(&Colored_vertex::TRIANGLE))?;
+
+        //Opened first, manifests may refer to stored shaders by hash
+        if let Some(path) = config.shader_store_path {
+            context.shader_library.open_store(path)?;
+        }
+        if let Some(path) = config.pipeline_manifest_path {
+            context.load_pipeline_manifest(path)?;
+        }
+
+        Ok(context)
+    }
+
+    fn create_vertex_buffer(&mut self, layout: Vertex_layout, data: &[u8]) -> Vulkan_result<Vertex_buffer_handle>{
+        self.vertex_buffers.create(&self.device, &mut self.allocator, self.command_context.pool, self.graphics_queue, layout, data)
+    }
+
+    fn release_resources(&mut self){
+        self.vertex_buffers.destroy_all(&self.device, &mut self.allocator);
+        self.index_buffers.destroy_all(&self.device, &mut self.allocator);
+        self.uniform_buffers.destroy_all(&self.device, &mut self.allocator);
+        self.textures.destroy_all(&self.device, &mut self.allocator);
+    }
+
+    #[allow(unused_variables)]
+    fn create_texture(&mut self, image_data: &Image_data) -> Vulkan_result<Texture_handle>{
+        let limit = self.properties.limits.max_image_dimension2_d;
+        if image_data.width == 0 || image_data.height == 0 || image_data.width > limit || image_data.height > limit {
+            save_log!(Log_level::General, Level::Error, "Failed to create texture: {}x{}, the device allows 1 to {} per side", image_data.width, image_data.height, limit);
+            return Err(Status_code::Failure)
+        }
+        if image_data.pixels.len() as u64 != image_data.width as u64 * image_data.height as u64 * 4 {
+            save_log!(Log_level::General, Level::Error, "Failed to create texture: {} bytes are not {}x{} RGBA8 pixels", image_data.pixels.len(), image_data.width, image_data.height);
+            return Err(Status_code::Failure)
+        }
+
+        self.textures.create(&self.device, &mut self.allocator, self.command_context.pool, self.graphics_queue, image_data)
+    }
+
+    fn create_index_buffer(&mut self, index_type: Index_type, data: &[u8]) -> Vulkan_result<Index_buffer_handle>{
+        self.index_buffers.create(&self.device, &mut self.allocator, self.command_context.pool, self.graphics_queue, index_type, data)
+    }
+
+    #[allow(unused_variables)]
+    fn load_pipeline_manifest(&mut self, path: impl AsRef<Path>) -> Vulkan_result<Vec<Pipeline_handle>>{
+        let manifest = Pipeline_manifest::load(path).map_err(|error| {
+            save_log!(Log_level::General, Level::Error, "{}", error);
+
+            Status_code::Failure
+        })?;
+
+        self.graphics_pipelines.load_manifest(&self.device, &manifest, &self.shader_library)
+    }
+
+    //Runs between frames, so no command buffer is being recorded while pipelines are swapped
+    #[cfg(all(debug_assertions, feature = "hot_reload"))]
+    fn reload_shaders(&mut self) -> Vulkan_result<()> {
+        let Some(shader_watcher) = &self.shader_watcher else {
+            return Ok(())
+        };
+
+        let sources = shader_watcher.poll();
+        if sources.is_empty() {
+            return Ok(())
+        }
+
+        save_log!(Log_level::General, Level::Info, "Reloading shaders {:?}", sources.iter().map(|source| &source.name).collect::<Vec<_>>());
+
+        self.graphics_pipelines.reload_shaders(&self.device, &self.pipeline_cache, &mut self.descriptor_sets, &sources, |source, features| shader_watcher.compile(source, features))
+    }
+
+    fn set_render_format(&mut self, format: vk::Format) -> Vulkan_result<()> {
+        if format == self.render_format {
+            return Ok(())
+        }
+
+        save_log!(Log_level::General, Level::Warn, "Surface format {:?} differs from prewarmed format {:?}, rebuilding pipelines", format, self.render_format);
+
+        //Jobs still in the queue reference the old render pass, let them finish before it goes away
+        self.graphics_pipelines.wait_all();
+
+        unsafe {
+            self.device.device_wait_idle()?;
+            self.device.destroy_render_pass(self.render_pass, None);
+        }
+
+        self.render_format = format;
+        self.render_pass = create_render_pass(&self.device, format)?;
+        self.graphics_pipelines.rebuild(&self.device, self.render_pass)?;
+
+        Ok(())
+    }
+
+    fn get_vulkan_entry(vulkan_path: Option<PathBuf>) -> Vulkan_result<ash::Entry> {
         Ok(unsafe {
             if let Some(path) = vulkan_path {
                 ash::Entry::load_from(path)?
@@ -215,16 +726,16 @@ impl Vulkan_application {
     }
     
     #[allow(unused_mut)]
-    fn get_instance(vulkan_entry: &ash::Entry, display_handle: RawDisplayHandle) -> Vulkan_result<ash::Instance> {
+    fn get_instance(vulkan_entry: &ash::Entry, surface_extensions: &[&'static CStr]) -> Vulkan_result<ash::Instance> {
         let app_info = vk::ApplicationInfo::default()
             .application_name(c"Rust Vulkan Application")
             .application_version(vk::make_api_version(0, 1, 0, 0))
             .engine_name(c"Han_feng\'s Engine")
             .engine_version(vk::make_api_version(0, 1, 0, 0))
-            .api_version(vk::API_VERSION_1_0);
+            .api_version(API_VERSION);
 
         let mut enabled_layers = vec![];
-        let mut enabled_extensions = ash_window::enumerate_required_extensions(display_handle)?.to_vec();
+        let mut enabled_extensions = surface_extensions.iter().map(|extension| extension.as_ptr()).collect::<Vec<_>>();
 
         cfg_if::cfg_if! {
             if #[cfg(debug_assertions)] {
@@ -253,10 +764,13 @@ impl Vulkan_application {
         Ok(instance)
     }
 
-    fn get_physical_device_and_indices(instance: &ash::Instance, surface: &Surface) -> Vulkan_result<(vk::PhysicalDevice, Queue_family_indices)>{
+    fn get_physical_device_and_indices(instance: &ash::Instance, presentable: bool) -> Vulkan_result<(vk::PhysicalDevice, Queue_family_indices)>{
         let device_pack = unsafe { instance.enumerate_physical_devices()? .into_iter()
             .filter(|&physical_device| {
-                if let Ok(properties) = instance.enumerate_device_extension_properties(physical_device) && let Ok(support) = Swap_chain_supports::physical_device_check(physical_device, &surface) && support {
+                if !presentable {
+                    true
+                }
+                else if let Ok(properties) = instance.enumerate_device_extension_properties(physical_device) {
                     let extensions = properties.into_iter().map(|extension| extension.extension_name.as_ptr().to_string()).collect::<HashSet<_>>();
                     DEVICE_EXTENSIONS.into_iter().all(|extension| extensions.contains(&extension.to_string()))
                 }
@@ -266,7 +780,7 @@ impl Vulkan_application {
             })
             .filter_map(|physical_device|{
                 let properties = instance.get_physical_device_properties(physical_device);
-                if let Ok(indices) = Queue_family_indices::new(instance, physical_device, surface){
+                if let Some(indices) = Queue_family_indices::new(instance, physical_device){
                     Some((physical_device, indices, properties))
                 }
                 else {
@@ -292,8 +806,10 @@ impl Vulkan_application {
         Ok((device_pack.0, device_pack.1))
     }
 
-    fn get_device_and_queues(instance: &ash::Instance, physical_device: vk::PhysicalDevice, indices: &Queue_family_indices) -> Vulkan_result<(ash::Device, Queues)> {
-        let queue_infos = indices.clone().filter_map(|index| index).collect::<HashSet<_>>().into_iter().map(|index| {
+    fn get_device(instance: &ash::Instance, physical_device: vk::PhysicalDevice, presentable: bool) -> Vulkan_result<ash::Device> {
+        //The present family is only known once a surface arrives, so every family gets a queue
+        let family_count = unsafe { instance.get_physical_device_queue_family_properties(physical_device) }.len() as u32;
+        let queue_infos = (0..family_count).map(|index| {
             vk::DeviceQueueCreateInfo::default()
                 .queue_family_index(index)
                 .queue_priorities(&[1.0])
@@ -303,31 +819,32 @@ impl Vulkan_application {
         let create_info = vk::DeviceCreateInfo::default()
             .queue_create_infos(&queue_infos)
             .enabled_features(&device_features)
-            .enabled_extension_names(&DEVICE_EXTENSIONS);
+            .enabled_extension_names(if presentable { &DEVICE_EXTENSIONS[..] } else { &[] });
 
         let device = unsafe { instance.create_device(physical_device, &create_info, None)? };
         save_log!(Log_level::General, Level::Info, "Successfully created device");
-        let queues = Queues::new(&device, &indices);
-        save_log!(Log_level::General, Level::Info, "Successfully get queues");
 
-        Ok((device, queues))
+        Ok(device)
     }
 }
 
-impl Drop for Vulkan_application {
+impl Drop for Vulkan_context {
     fn drop(&mut self) {
         unsafe {
             self.device.device_wait_idle().unwrap();
-            
-            self.sync_objects.destroy(&self.device);
 
             self.command_context.destroy(&self.device);
+            self.descriptor_sets.destroy_all(&self.device);
+            self.release_resources();
+            self.allocator.destroy(&self.device);
 
+            let shader_hashes = self.graphics_pipelines.get_shader_hashes();
             self.graphics_pipelines.destroy(&self.device);
 
-            self.swap_chain.destroy(&self.device);
+            let _ = self.pipeline_cache.save(&self.device, &shader_hashes);
+            self.pipeline_cache.destroy(&self.device);
 
-            self.surface.destroy();
+            self.device.destroy_render_pass(self.render_pass, None);
 
             #[cfg(debug_assertions)]
             self._debug_messenger.destroy();
@@ -338,6 +855,62 @@ impl Drop for Vulkan_application {
     }
 }
 
+impl Presentation {
+    fn new(context: &mut Vulkan_context, window: Window) -> Vulkan_result<Self> {
+        let surface = Surface::new(&context.vulkan_entry, &context.instance, window)?;
+
+        let result = Self::create(context, &surface, window);
+        if result.is_err() {
+            surface.destroy();
+        }
+
+        let (indices, present_queue, swap_chain, sync_objects) = result?;
+
+        Ok(Presentation{
+            window, surface, indices, present_queue, swap_chain, sync_objects
+        })
+    }
+
+    fn create(context: &mut Vulkan_context, surface: &Surface, window: Window) -> Vulkan_result<(Queue_family_indices, vk::Queue, Swap_chain, Sync_objects)> {
+        if !Swap_chain_supports::physical_device_check(context.physical_device, surface)? {
+            save_log!(Log_level::General, Level::Error, "Physical device can not present to the surface");
+            return Err(Status_code::Failure)
+        }
+
+        let mut indices = context.indices.clone();
+        indices.find_present_family(&context.instance, context.physical_device, surface)?;
+        let present_queue = unsafe { context.device.get_device_queue(indices.present_family.unwrap(), 0) };
+
+        let mut swap_chain = Swap_chain::new(&context.instance, &context.device, context.physical_device, &indices, surface, &window)?;
+        if let Err(code) = context.set_render_format(swap_chain.surface_format.format).and_then(|_| swap_chain.update_images(&context.device, context.render_pass)) {
+            swap_chain.destroy(&context.device);
+            return Err(code)
+        }
+
+        let sync_objects = match Sync_objects::new(&context.device, swap_chain.images.len()) {
+            Ok(sync_objects) => sync_objects,
+            Err(code) => {
+                swap_chain.destroy(&context.device);
+                return Err(code)
+            }
+        };
+
+        Ok((indices, present_queue, swap_chain, sync_objects))
+    }
+
+    fn recreate_swap_chain(&mut self, context: &Vulkan_context) -> Vulkan_result<()> {
+        self.swap_chain.recreate(&context.device, context.physical_device, &self.indices, &self.surface, &self.window, context.render_pass)
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        self.sync_objects.destroy(device);
+
+        self.swap_chain.destroy(device);
+
+        self.surface.destroy();
+    }
+}
+
 #[cfg(debug_assertions)]
 #[allow(unsafe_op_in_unsafe_fn)]
 unsafe extern "system" fn debug_callback(
@@ -387,7 +960,7 @@ impl Debug_messenger{
 impl Window{
     pub fn new(window_handle: *mut c_void, width: u32, height: u32) -> Vulkan_result<Self>{
         Ok(Window{
-            display_handle: raw_window_handle::OhosDisplayHandle::new().into(),
+            display_handle: Self::get_display_handle(),
             window_handle: raw_window_handle::OhosNdkWindowHandle::new(NonNull::new(window_handle).ok_or_else(|| {
             save_log!(Log_level::General, Level::Error, "Failed to create window handle");
             Status_code::Failure
@@ -395,6 +968,9 @@ impl Window{
             width, height
         })
     }
+    pub fn get_display_handle() -> RawDisplayHandle{
+        raw_window_handle::OhosDisplayHandle::new().into()
+    }
 }
 
 impl Surface {
@@ -417,39 +993,35 @@ impl Surface {
 }
 
 impl Queue_family_indices{
-    fn new(instance: &ash::Instance, physical_device: vk::PhysicalDevice, surface: &Surface) -> Vulkan_result<Self>{
-        let mut indices = Queue_family_indices::default();
+    fn new(instance: &ash::Instance, physical_device: vk::PhysicalDevice) -> Option<Self>{
+        let graphics_family = unsafe { instance.get_physical_device_queue_family_properties(physical_device) }.iter()
+            .position(|properties| properties.queue_flags.contains(vk::QueueFlags::GRAPHICS));
 
-        for (index, properties) in unsafe { instance.get_physical_device_queue_family_properties(physical_device) }.iter().enumerate(){
-            if properties.queue_flags.contains(vk::QueueFlags::GRAPHICS){
-                indices.graphics_family = Some(index as u32);
-            }
-            if unsafe { surface.instance.get_physical_device_surface_support(physical_device, index as u32, surface.surface)? } {
-                indices.present_family = Some(index as u32);
-            }
-
-            if indices.is_complete(){
-                break;
-            }
+        if graphics_family.is_none(){
+            save_log!(Log_level::General, Level::Info, "Failed to find graphics queue family indices");
         }
 
-        if indices.is_complete(){
-            Ok(indices)
-        }
-        else{
-            if indices.graphics_family.is_none(){
-                save_log!(Log_level::General, Level::Info, "Failed to find graphics queue family indices");
-            }
-            if indices.present_family.is_none(){
-                save_log!(Log_level::General, Level::Info, "Failed to find present queue family indices");
-            }
+        graphics_family.map(|index| Queue_family_indices{
+            graphics_family: Some(index as u32),
+            ..Default::default()
+        })
+    }
 
-            Err(Status_code::Failure)
+    fn find_present_family(&mut self, instance: &ash::Instance, physical_device: vk::PhysicalDevice, surface: &Surface) -> Vulkan_result<()>{
+        let family_count = unsafe { instance.get_physical_device_queue_family_properties(physical_device) }.len() as u32;
+
+        //Prefer presenting from the graphics family so the swap chain images need no sharing
+        let candidates = self.graphics_family.into_iter().chain((0..family_count).filter(|&index| Some(index) != self.graphics_family));
+        for index in candidates{
+            if unsafe { surface.instance.get_physical_device_surface_support(physical_device, index, surface.surface)? } {
+                self.present_family = Some(index);
+                return Ok(())
+            }
         }
-    }
 
-    fn is_complete(&self) -> bool{
-        self.graphics_family.is_some() && self.present_family.is_some()
+        save_log!(Log_level::General, Level::Error, "Failed to find present queue family indices");
+
+        Err(Status_code::Failure)
     }
 }
 
@@ -473,17 +1045,6 @@ impl Iterator for Queue_family_indices{
     }
 }
 
-impl Queues{
-    fn new(device: &ash::Device, indices: &Queue_family_indices) -> Self{
-        unsafe {
-            Queues {
-                graphics_queue: device.get_device_queue(indices.graphics_family.unwrap(), 0),
-                present_queue: device.get_device_queue(indices.present_family.unwrap(), 0),
-            }
-        }
-    }
-}
-
 impl Swap_chain_supports{
     fn new(physical_device: vk::PhysicalDevice, surface: &Surface) -> Vulkan_result<Self>{
         let supports = unsafe {
@@ -508,7 +1069,7 @@ impl Swap_chain_supports{
 
     fn choose_format(&self) -> vk::SurfaceFormatKHR{
         for surface_format in self.formats.iter(){
-            if surface_format.format == vk::Format::R8G8B8A8_SRGB && surface_format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR{
+            if surface_format.format == PREFERRED_FORMAT && surface_format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR{
                 return surface_format.clone()
             }
         }
@@ -545,20 +1106,12 @@ impl Swap_chain{
 
         save_log!(Log_level::General, Level::Info, "Successfully create swap chain");
 
-        let render_pass = Self::create_render_pass(device, surface_format.format)?;
-
-        save_log!(Log_level::General, Level::Info, "Successfully created render pass");
-
-        let mut result = Swap_chain{
-            device: swap_chain_device, swap_chain, surface_format, extent, render_pass,
+        Ok(Swap_chain{
+            device: swap_chain_device, swap_chain, surface_format, extent,
             images: vec![],
             image_views: vec![],
             frame_buffers: vec![]
-        };
-
-        result.update_images(device)?;
-
-        Ok(result)
+        })
     }
 
     fn create_swap_chain(device: &ash::khr::swapchain::Device, physical_device: vk::PhysicalDevice, indices: &Queue_family_indices, surface: &Surface, window: &Window) -> Vulkan_result<(vk::SwapchainKHR, vk::SurfaceFormatKHR, vk::Extent2D)> {
@@ -598,50 +1151,7 @@ impl Swap_chain{
         Ok((unsafe { device.create_swapchain(&create_info, None) }?, surface_format, extent))
     }
 
-    fn create_render_pass(device: &ash::Device, image_format: vk::Format) -> Vulkan_result<vk::RenderPass> {
-        let attachment_descriptions = [
-            vk::AttachmentDescription::default()
-                .format(image_format)
-                .samples(vk::SampleCountFlags::TYPE_1)
-                .load_op(vk::AttachmentLoadOp::CLEAR)
-                .store_op(vk::AttachmentStoreOp::STORE)
-                .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
-                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
-                .initial_layout(vk::ImageLayout::UNDEFINED)
-                .final_layout(vk::ImageLayout::PRESENT_SRC_KHR)
-        ];
-
-        let attachment_refs = [
-            vk::AttachmentReference::default()
-                .attachment(0)
-                .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
-        ];
-
-        let subpasses = [
-            vk::SubpassDescription::default()
-                .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
-                .color_attachments(&attachment_refs)
-        ];
-
-        let dependencies = [
-            vk::SubpassDependency::default()
-                .src_subpass(vk::SUBPASS_EXTERNAL)
-                .dst_subpass(0)
-                .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
-                .src_access_mask(vk::AccessFlags::empty())
-                .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
-                .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
-        ];
-
-        let create_info = vk::RenderPassCreateInfo::default()
-            .attachments(&attachment_descriptions)
-            .subpasses(&subpasses)
-            .dependencies(&dependencies);
-
-        Ok(unsafe { device.create_render_pass(&create_info, None)? })
-    }
-
-    fn update_images(&mut self, device: &ash::Device) -> Vulkan_result<()>{
+    fn update_images(&mut self, device: &ash::Device, render_pass: vk::RenderPass) -> Vulkan_result<()>{
         self.clean_images(device);
 
         unsafe {
@@ -669,7 +1179,7 @@ impl Swap_chain{
             self.frame_buffers = self.image_views.iter().map(|&image_view| {
                 let attachments = [image_view];
                 let create_info = vk::FramebufferCreateInfo::default()
-                    .render_pass(self.render_pass)
+                    .render_pass(render_pass)
                     .attachments(&attachments)
                     .width(self.extent.width)
                     .height(self.extent.height)
@@ -684,7 +1194,7 @@ impl Swap_chain{
         Ok(())
     }
 
-    fn recreate(&mut self, device: &ash::Device, physical_device: vk::PhysicalDevice, indices: &Queue_family_indices, surface: &Surface, window: &Window) -> Vulkan_result<()>{
+    fn recreate(&mut self, device: &ash::Device, physical_device: vk::PhysicalDevice, indices: &Queue_family_indices, surface: &Surface, window: &Window, render_pass: vk::RenderPass) -> Vulkan_result<()>{
         unsafe {
             device.device_wait_idle()?;
             self.device.destroy_swapchain(self.swap_chain, None);
@@ -692,7 +1202,7 @@ impl Swap_chain{
 
         (self.swap_chain, self.surface_format, self.extent) = Self::create_swap_chain(&self.device, physical_device, indices, surface, window)?;
 
-        self.update_images(device)?;
+        self.update_images(device, render_pass)?;
 
         save_log!(Log_level::General, Level::Info, "Successfully recreate swap chain");
 
@@ -710,145 +1220,10 @@ impl Swap_chain{
         unsafe {
             self.clean_images(device);
             self.device.destroy_swapchain(self.swap_chain, None);
-            device.destroy_render_pass(self.render_pass, None);
         }
     }
 }
 
-impl Graphics_pipelines {
-    fn new(device: &ash::Device, render_pass: vk::RenderPass) -> Vulkan_result<Self>{
-        let vertex_shader = Self::get_shader(device, VERTEX_SHADER)?;
-        let fragment_shader = Self::get_shader(device, FRAGMENT_SHADER)?;
-
-        //Shader stages
-        let stages = [
-            vk::PipelineShaderStageCreateInfo::default()
-                .stage(vk::ShaderStageFlags::VERTEX)
-                .module(vertex_shader)
-                .name(c"main"),
-            vk::PipelineShaderStageCreateInfo::default()
-                .stage(vk::ShaderStageFlags::FRAGMENT)
-                .module(fragment_shader)
-                .name(c"main")
-        ];
-
-        //Dynamic states
-        let dynamic_state_info = vk::PipelineDynamicStateCreateInfo::default()
-            .dynamic_states(&[
-                vk::DynamicState::VIEWPORT,
-                vk::DynamicState::SCISSOR,
-            ]);
-
-        //Vertex Input
-        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default();
-
-        //Input assembly
-        let input_assembly_info = vk::PipelineInputAssemblyStateCreateInfo::default()
-            .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
-            .primitive_restart_enable(false);
-
-        //Viewport
-        let viewport_info = vk::PipelineViewportStateCreateInfo::default()
-            .viewport_count(1)
-            .scissor_count(1);
-
-        //Rasterizer
-        let rasterizer_info = vk::PipelineRasterizationStateCreateInfo::default()
-            .depth_clamp_enable(false)
-            .rasterizer_discard_enable(false)
-            .polygon_mode(vk::PolygonMode::FILL)
-            .line_width(1.0)
-            .cull_mode(vk::CullModeFlags::BACK)
-            .front_face(vk::FrontFace::CLOCKWISE)
-            .depth_bias_enable(false);
-
-        //Multisample
-        let multisample_info = vk::PipelineMultisampleStateCreateInfo::default()
-            .sample_shading_enable(false)
-            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
-
-        //Depth stencil
-        let _depth_stencil_info = vk::PipelineDepthStencilStateCreateInfo::default();
-
-        //Color blend
-        let color_blend_attachments = [
-            vk::PipelineColorBlendAttachmentState::default()
-            .color_write_mask(vk::ColorComponentFlags::RGBA)
-            .blend_enable(true)
-            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
-            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
-            .color_blend_op(vk::BlendOp::ADD)
-            .src_alpha_blend_factor(vk::BlendFactor::ONE)
-            .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
-            .alpha_blend_op(vk::BlendOp::ADD)
-        ];
-
-        let color_blend_info = vk::PipelineColorBlendStateCreateInfo::default()
-            .logic_op_enable(false)
-            .attachments(&color_blend_attachments)
-            .blend_constants([0.0, 0.0, 0.0, 0.0]);
-
-
-        //Layout
-        let layout_info = vk::PipelineLayoutCreateInfo::default();
-
-        let layout = unsafe { device.create_pipeline_layout(&layout_info, None)? };
-
-        //Creation
-        let create_infos = [
-            vk::GraphicsPipelineCreateInfo::default()
-            .stages(&stages)
-            .vertex_input_state(&vertex_input_info)
-            .input_assembly_state(&input_assembly_info)
-            .viewport_state(&viewport_info)
-            .rasterization_state(&rasterizer_info)
-            .multisample_state(&multisample_info)
-            .color_blend_state(&color_blend_info)
-            .dynamic_state(&dynamic_state_info)
-            .layout(layout)
-            .render_pass(render_pass)
-            .subpass(0)
-        ];
-
-        let pipelines = unsafe { device.create_graphics_pipelines(vk::PipelineCache::null(), &create_infos, None).map_err(|(_, err)| err)? };
-
-        save_log!(Log_level::General, Level::Info, "Successfully created graphics pipeline");
-
-        unsafe {
-            device.destroy_shader_module(vertex_shader, None);
-            device.destroy_shader_module(fragment_shader, None);
-        }
-
-        Ok(Graphics_pipelines{
-            layout, pipelines
-        })
-    }
-
-    fn destroy(&self, device: &ash::Device){
-        unsafe {
-            self.pipelines.iter().for_each(|&pipeline| device.destroy_pipeline(pipeline, None));
-            device.destroy_pipeline_layout(self.layout, None);
-        }
-    }
-
-    fn get_shader(device: &ash::Device, shader_data: &[u8]) -> Vulkan_result<vk::ShaderModule>{
-        unsafe {
-            let create_info = vk::ShaderModuleCreateInfo::default()
-                .code(std::slice::from_raw_parts(shader_data.as_ptr() as *const _, shader_data.len()/4));
-
-            Ok(device.create_shader_module(&create_info, None)?)
-        }
-    }
-}
-
-impl Index<usize> for Graphics_pipelines {
-    type Output = vk::Pipeline;
-
-    fn index(&self, index: usize) -> &Self::Output {
-        &self.pipelines[index]
-    }
-}
-
 impl Command_context{
     fn new(device: &ash::Device, indices: &Queue_family_indices) -> Vulkan_result<Self>{
         let pool_info = vk::CommandPoolCreateInfo::default()
@@ -871,7 +1246,7 @@ impl Command_context{
         })
     }
 
-    fn get_draw_buffer(&self, device: &ash::Device, index: usize, image_index: usize, swap_chain: &Swap_chain, graphics_pipelines: &Graphics_pipelines) -> Vulkan_result<vk::CommandBuffer>{
+    fn get_draw_buffer(&self, device: &ash::Device, index: usize, image_index: usize, swap_chain: &Swap_chain, render_pass: vk::RenderPass, draws: &[Draw]) -> Vulkan_result<vk::CommandBuffer>{
         let buffer = self.draw_buffers[index];
 
         let begin_info = vk::CommandBufferBeginInfo::default();
@@ -880,7 +1255,7 @@ impl Command_context{
             vk::ClearValue{color: vk::ClearColorValue{float32: [0.0, 0.0, 0.0, 0.0]}}
         ];
         let render_pass_info = vk::RenderPassBeginInfo::default()
-            .render_pass(swap_chain.render_pass)
+            .render_pass(render_pass)
             .framebuffer(swap_chain.frame_buffers[image_index])
             .render_area(vk::Rect2D{offset: vk::Offset2D{x: 0, y: 0}, extent: swap_chain.extent})
             .clear_values(&clear_colors);
@@ -905,10 +1280,10 @@ impl Command_context{
             device.begin_command_buffer(buffer, &begin_info)?;
 
             device.cmd_begin_render_pass(buffer, &render_pass_info, vk::SubpassContents::INLINE);
-            device.cmd_bind_pipeline(buffer, vk::PipelineBindPoint::GRAPHICS, graphics_pipelines[0]);
+            //Every pipeline shares the same dynamic viewport and scissor
             device.cmd_set_viewport(buffer, 0, &viewports);
             device.cmd_set_scissor(buffer, 0, &scissors);
-            device.cmd_draw(buffer, 3, 1, 0, 0);
+            draws.iter().for_each(|draw| draw.record(device, buffer));
             device.cmd_end_render_pass(buffer);
 
             device.end_command_buffer(buffer)?;
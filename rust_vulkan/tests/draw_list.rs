@@ -0,0 +1,26 @@
+//Made by Han_feng
+
+use rust_vulkan::{get_draw_range, Draw_error};
+
+#[test]
+fn whole_buffer(){
+    //A count of zero takes everything from first
+    assert_eq!(get_draw_range(0, 0, 36), Ok((0, 36)));
+    assert_eq!(get_draw_range(6, 0, 36), Ok((6, 30)));
+    assert_eq!(get_draw_range(36, 0, 36), Ok((36, 0)));
+    assert_eq!(get_draw_range(37, 0, 36), Err(Draw_error::Out_of_range{first: 37, count: 0, available: 36}));
+}
+
+#[test]
+fn explicit_count(){
+    assert_eq!(get_draw_range(6, 30, 36), Ok((6, 30)));
+    assert_eq!(get_draw_range(6, 31, 36), Err(Draw_error::Out_of_range{first: 6, count: 31, available: 36}));
+    assert_eq!(get_draw_range(0, 1, 0), Err(Draw_error::Out_of_range{first: 0, count: 1, available: 0}));
+}
+
+#[test]
+fn overflow(){
+    assert_eq!(get_draw_range(u32::MAX, 2, u32::MAX), Err(Draw_error::Out_of_range{first: u32::MAX, count: 2, available: u32::MAX}));
+    assert_eq!(get_draw_range(1, u32::MAX, u32::MAX), Err(Draw_error::Out_of_range{first: 1, count: u32::MAX, available: u32::MAX}));
+    assert_eq!(get_draw_range(u32::MAX, 0, u32::MAX), Ok((u32::MAX, 0)));
+}
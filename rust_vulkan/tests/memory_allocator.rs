@@ -0,0 +1,53 @@
+//Made by Han_feng
+
+use rust_vulkan::{Free_list, Memory_stats};
+
+const BLOCK_SIZE: u64 = 1024;
+
+#[test]
+fn alignment_padding(){
+    let mut ranges = Free_list::new(BLOCK_SIZE);
+
+    assert_eq!(ranges.allocate(10, 4), Some(0));
+    //The padding in front of the aligned offset stays free
+    assert_eq!(ranges.allocate(100, 256), Some(256));
+    assert_eq!(ranges.get_free_ranges(), &[(10, 246), (356, 668)]);
+
+    //Padding is reused by a later allocation that fits
+    assert_eq!(ranges.allocate(200, 8), Some(16));
+    assert_eq!(ranges.get_free_ranges(), &[(10, 6), (216, 40), (356, 668)]);
+
+    assert_eq!(ranges.allocate(BLOCK_SIZE, 1), None);
+}
+
+#[test]
+fn merge_in_both_orders(){
+    for order in [[0, 1, 2], [2, 1, 0], [0, 2, 1], [1, 0, 2]] {
+        let mut ranges = Free_list::new(BLOCK_SIZE);
+        let allocations = [(ranges.allocate(100, 1).unwrap(), 100), (ranges.allocate(200, 64).unwrap(), 200), (ranges.allocate(300, 16).unwrap(), 300)];
+
+        order.into_iter().for_each(|index| ranges.free(allocations[index].0, allocations[index].1));
+
+        assert_eq!(ranges.get_free_ranges(), &[(0, BLOCK_SIZE)], "freed in order {:?}", order);
+        assert_eq!(ranges.get_stats().allocation_count, 0);
+    }
+}
+
+#[test]
+fn stats(){
+    let mut first = Free_list::new(BLOCK_SIZE);
+    let mut second = Free_list::new(BLOCK_SIZE * 2);
+
+    let offset = first.allocate(10, 1).unwrap();
+    first.allocate(100, 256).unwrap();
+    second.allocate(500, 1).unwrap();
+
+    //Alignment padding is not counted as used
+    assert_eq!(first.get_stats(), Memory_stats{block_count: 1, allocation_count: 2, reserved_bytes: BLOCK_SIZE, used_bytes: 110});
+
+    let total = [first.get_stats(), second.get_stats()].into_iter().sum::<Memory_stats>();
+    assert_eq!(total, Memory_stats{block_count: 2, allocation_count: 3, reserved_bytes: BLOCK_SIZE * 3, used_bytes: 610});
+
+    first.free(offset, 10);
+    assert_eq!((first.get_stats().allocation_count, first.get_stats().used_bytes), (1, 100));
+}
@@ -0,0 +1,63 @@
+//Made by Han_feng
+
+use rust_vulkan::{validate_pipeline_cache, Pipeline_cache_error, Pipeline_cache_header, PIPELINE_CACHE_HEADER_SIZE};
+
+const VENDOR_ID: u32 = 0x13b5;
+const DEVICE_ID: u32 = 0x92020010;
+const UUID: [u8; 16] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+
+fn cache_data(header_size: u32, header_version: u32, vendor_id: u32, device_id: u32, uuid: [u8; 16], payload: usize) -> Vec<u8>{
+    let mut data = vec![];
+    data.extend_from_slice(&header_size.to_ne_bytes());
+    data.extend_from_slice(&header_version.to_ne_bytes());
+    data.extend_from_slice(&vendor_id.to_ne_bytes());
+    data.extend_from_slice(&device_id.to_ne_bytes());
+    data.extend_from_slice(&uuid);
+    data.resize(data.len() + payload, 0xab);
+    data
+}
+
+#[test]
+fn valid_header(){
+    let data = cache_data(32, 1, VENDOR_ID, DEVICE_ID, UUID, 64);
+    let header = validate_pipeline_cache(&data, VENDOR_ID, DEVICE_ID, &UUID).unwrap();
+
+    assert_eq!(header, Pipeline_cache_header{
+        header_size: 32,
+        header_version: 1,
+        vendor_id: VENDOR_ID,
+        device_id: DEVICE_ID,
+        pipeline_cache_uuid: UUID,
+    });
+}
+
+#[test]
+fn truncated_header(){
+    let data = cache_data(32, 1, VENDOR_ID, DEVICE_ID, UUID, 0);
+
+    assert_eq!(validate_pipeline_cache(&[], VENDOR_ID, DEVICE_ID, &UUID), Err(Pipeline_cache_error::Truncated{size: 0}));
+    assert_eq!(validate_pipeline_cache(&data[..PIPELINE_CACHE_HEADER_SIZE-1], VENDOR_ID, DEVICE_ID, &UUID), Err(Pipeline_cache_error::Truncated{size: PIPELINE_CACHE_HEADER_SIZE-1}));
+}
+
+#[test]
+fn corrupt_header(){
+    let too_small = cache_data(16, 1, VENDOR_ID, DEVICE_ID, UUID, 0);
+    assert_eq!(Pipeline_cache_header::parse(&too_small), Err(Pipeline_cache_error::Invalid_header_size{header_size: 16, size: 32}));
+
+    let too_large = cache_data(64, 1, VENDOR_ID, DEVICE_ID, UUID, 8);
+    assert_eq!(Pipeline_cache_header::parse(&too_large), Err(Pipeline_cache_error::Invalid_header_size{header_size: 64, size: 40}));
+
+    let bad_version = cache_data(32, 2, VENDOR_ID, DEVICE_ID, UUID, 8);
+    assert_eq!(Pipeline_cache_header::parse(&bad_version), Err(Pipeline_cache_error::Unsupported_header_version(2)));
+}
+
+#[test]
+fn mismatched_device(){
+    let data = cache_data(32, 1, VENDOR_ID, DEVICE_ID, UUID, 8);
+
+    assert_eq!(validate_pipeline_cache(&data, 0x10de, DEVICE_ID, &UUID), Err(Pipeline_cache_error::Vendor_mismatch{expected: 0x10de, found: VENDOR_ID}));
+    assert_eq!(validate_pipeline_cache(&data, VENDOR_ID, 1, &UUID), Err(Pipeline_cache_error::Device_mismatch{expected: 1, found: DEVICE_ID}));
+
+    let other_uuid = [0; 16];
+    assert_eq!(validate_pipeline_cache(&data, VENDOR_ID, DEVICE_ID, &other_uuid), Err(Pipeline_cache_error::Uuid_mismatch{expected: other_uuid, found: UUID}));
+}
@@ -0,0 +1,79 @@
+//Made by Han_feng
+
+use rust_vulkan::{Blend_mode, Cull_mode, Manifest_error, Pipeline_entry, Pipeline_manifest, Render_format, Topology};
+
+#[test]
+fn default_entry(){
+    let manifest = Pipeline_manifest::parse(r#"{
+        "pipelines": [
+            {"name": "sprite", "vertex_shader": "vertex_shader", "fragment_shader": "fragment_shader"}
+        ]
+    }"#).unwrap();
+
+    assert_eq!(manifest.pipelines, vec![Pipeline_entry{
+        name: "sprite".to_string(),
+        vertex_shader: "vertex_shader".to_string(),
+        fragment_shader: "fragment_shader".to_string(),
+        topology: Topology::Triangle_list,
+        blend: Blend_mode::Alpha,
+        cull: Cull_mode::Back,
+        format: None,
+        features: vec![],
+        vertex_layout: None,
+        instance_layout: None,
+    }]);
+}
+
+#[test]
+fn full_entry(){
+    let manifest = Pipeline_manifest::parse(r#"{
+        "pipelines": [
+            {
+                "name": "lines",
+                "vertex_shader": "vertex_shader",
+                "fragment_shader": "fragment_shader",
+                "topology": "line_strip",
+                "blend": "additive",
+                "cull": "none",
+                "format": "b8g8r8a8_unorm",
+                "features": ["TEXTURED", "ALPHA_TEST"]
+            }
+        ]
+    }"#).unwrap();
+
+    let entry = &manifest.pipelines[0];
+    assert_eq!(entry.topology, Topology::Line_strip);
+    assert_eq!(entry.blend, Blend_mode::Additive);
+    assert_eq!(entry.cull, Cull_mode::None);
+    assert_eq!(entry.format, Some(Render_format::B8g8r8a8_unorm));
+    assert_eq!(entry.features, vec!["TEXTURED".to_string(), "ALPHA_TEST".to_string()]);
+}
+
+#[test]
+fn duplicate_name(){
+    let result = Pipeline_manifest::parse(r#"{
+        "pipelines": [
+            {"name": "sprite", "vertex_shader": "vertex_shader", "fragment_shader": "fragment_shader"},
+            {"name": "sprite", "vertex_shader": "vertex_shader", "fragment_shader": "fragment_shader", "blend": "opaque"}
+        ]
+    }"#);
+
+    assert_eq!(result, Err(Manifest_error::Duplicate_name("sprite".to_string())));
+}
+
+#[test]
+fn invalid_entry(){
+    let unknown_field = Pipeline_manifest::parse(r#"{
+        "pipelines": [
+            {"name": "sprite", "vertex_shader": "vertex_shader", "fragment_shader": "fragment_shader", "depth": true}
+        ]
+    }"#);
+    let unknown_value = Pipeline_manifest::parse(r#"{
+        "pipelines": [
+            {"name": "sprite", "vertex_shader": "vertex_shader", "fragment_shader": "fragment_shader", "blend": "multiply"}
+        ]
+    }"#);
+
+    assert!(matches!(unknown_field, Err(Manifest_error::Parse(_))));
+    assert!(matches!(unknown_value, Err(Manifest_error::Parse(_))));
+}
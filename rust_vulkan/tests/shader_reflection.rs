@@ -0,0 +1,169 @@
+//Made by Han_feng
+
+use ash::vk;
+use rust_vulkan::{find_embedded_shader, load_spirv, Descriptor_binding, Draw_command, Draw_error, Interface_variable, Pipeline_handle, Pipeline_reflection, Push_constant_range, Reflection_error, Shader_reflection, Vertex_buffer_handle, SPIRV_MAGIC};
+
+fn instruction(code: &mut Vec<u32>, opcode: u32, operands: &[u32]){
+    code.push(((operands.len() as u32 + 1) << 16) | opcode);
+    code.extend_from_slice(operands);
+}
+
+fn reflect_embedded(name: &str) -> Shader_reflection{
+    Shader_reflection::new(&load_spirv(find_embedded_shader(name).unwrap().code).unwrap()).unwrap()
+}
+
+//layout(location = 0) in vec2 position;
+//layout(push_constant) uniform Push{ mat4 transform; vec4 color; };
+//layout(set = 1, binding = 2) uniform Ubo{ vec4 tint; };
+//layout(set = 0, binding = 0) uniform sampler2D textures[4];
+fn vertex_module() -> Vec<u32>{
+    let mut code = vec![SPIRV_MAGIC, 0x00010000, 0, 101, 0];
+
+    instruction(&mut code, 15, &[0, 100, u32::from_le_bytes(*b"main"), 0, 4]); //OpEntryPoint
+    instruction(&mut code, 71, &[4, 30, 0]); //OpDecorate Location
+    instruction(&mut code, 72, &[7, 0, 35, 0]); //OpMemberDecorate Offset
+    instruction(&mut code, 72, &[7, 0, 7, 16]); //OpMemberDecorate MatrixStride
+    instruction(&mut code, 72, &[7, 1, 35, 64]); //OpMemberDecorate Offset
+    instruction(&mut code, 71, &[10, 2]); //OpDecorate Block
+    instruction(&mut code, 72, &[10, 0, 35, 0]); //OpMemberDecorate Offset
+    instruction(&mut code, 71, &[12, 34, 1]); //OpDecorate DescriptorSet
+    instruction(&mut code, 71, &[12, 33, 2]); //OpDecorate Binding
+    instruction(&mut code, 71, &[19, 34, 0]); //OpDecorate DescriptorSet
+    instruction(&mut code, 71, &[19, 33, 0]); //OpDecorate Binding
+
+    instruction(&mut code, 22, &[1, 32]); //OpTypeFloat
+    instruction(&mut code, 23, &[2, 1, 2]); //OpTypeVector
+    instruction(&mut code, 32, &[3, 1, 2]); //OpTypePointer Input
+    instruction(&mut code, 59, &[3, 4, 1]); //OpVariable Input
+    instruction(&mut code, 23, &[5, 1, 4]); //OpTypeVector
+    instruction(&mut code, 24, &[6, 5, 4]); //OpTypeMatrix
+    instruction(&mut code, 30, &[7, 6, 5]); //OpTypeStruct
+    instruction(&mut code, 32, &[8, 9, 7]); //OpTypePointer PushConstant
+    instruction(&mut code, 59, &[8, 9, 9]); //OpVariable PushConstant
+    instruction(&mut code, 30, &[10, 5]); //OpTypeStruct
+    instruction(&mut code, 32, &[11, 2, 10]); //OpTypePointer Uniform
+    instruction(&mut code, 59, &[11, 12, 2]); //OpVariable Uniform
+    instruction(&mut code, 25, &[13, 1, 1, 0, 0, 0, 1, 0]); //OpTypeImage 2D sampled
+    instruction(&mut code, 27, &[14, 13]); //OpTypeSampledImage
+    instruction(&mut code, 21, &[15, 32, 0]); //OpTypeInt
+    instruction(&mut code, 43, &[15, 16, 4]); //OpConstant
+    instruction(&mut code, 28, &[17, 14, 16]); //OpTypeArray
+    instruction(&mut code, 32, &[18, 0, 17]); //OpTypePointer UniformConstant
+    instruction(&mut code, 59, &[18, 19, 0]); //OpVariable UniformConstant
+
+    code
+}
+
+#[test]
+fn embedded_shaders(){
+    let vertex_shader = reflect_embedded("vertex_shader");
+    let fragment_shader = reflect_embedded("fragment_shader");
+
+    assert_eq!(vertex_shader.stage, vk::ShaderStageFlags::VERTEX);
+    assert_eq!(vertex_shader.entry_point, "main");
+    assert_eq!(vertex_shader.inputs.iter().map(|input| (input.location, input.format)).collect::<Vec<_>>(), vec![(0, vk::Format::R32G32_SFLOAT), (1, vk::Format::R32G32B32_SFLOAT)]);
+    assert_eq!(vertex_shader.outputs.iter().map(|output| (output.location, output.format)).collect::<Vec<_>>(), vec![(0, vk::Format::R32G32B32_SFLOAT)]);
+    assert_eq!(fragment_shader.stage, vk::ShaderStageFlags::FRAGMENT);
+    assert_eq!(fragment_shader.inputs.iter().map(|input| (input.location, input.format)).collect::<Vec<_>>(), vec![(0, vk::Format::R32G32B32_SFLOAT)]);
+
+    let pipeline = Pipeline_reflection::new(&[vertex_shader, fragment_shader]).unwrap();
+    assert_eq!(pipeline.entry_points, vec!["main".to_string(), "main".to_string()]);
+    assert!(pipeline.descriptor_bindings.is_empty());
+    assert_eq!(pipeline.vertex_inputs.len(), 2);
+    assert_eq!(pipeline.push_constants, None);
+    assert_eq!(pipeline.get_set_count(), 0);
+}
+
+#[test]
+fn resources(){
+    let reflection = Shader_reflection::new(&vertex_module()).unwrap();
+
+    assert_eq!(reflection.inputs, vec![Interface_variable{location: 0, format: vk::Format::R32G32_SFLOAT, name: None}]);
+    assert_eq!(reflection.push_constants, Some(Push_constant_range{offset: 0, size: 80, stages: vk::ShaderStageFlags::VERTEX}));
+    assert_eq!(reflection.descriptor_bindings, vec![
+        Descriptor_binding{set: 0, binding: 0, descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER, count: 4, stages: vk::ShaderStageFlags::VERTEX},
+        Descriptor_binding{set: 1, binding: 2, descriptor_type: vk::DescriptorType::UNIFORM_BUFFER, count: 1, stages: vk::ShaderStageFlags::VERTEX},
+    ]);
+
+    let pipeline = Pipeline_reflection::new(&[reflection]).unwrap();
+    assert_eq!(pipeline.get_set_count(), 2);
+    assert_eq!(pipeline.vertex_inputs.len(), 1);
+}
+
+#[test]
+fn push_constant_limit(){
+    let pipeline = Pipeline_reflection::new(&[Shader_reflection::new(&vertex_module()).unwrap()]).unwrap();
+
+    //Vulkan guarantees at least 128 bytes
+    assert_eq!(pipeline.check_push_constants(128), Ok(()));
+    assert_eq!(pipeline.check_push_constants(80), Ok(()));
+    assert_eq!(pipeline.check_push_constants(64), Err(Reflection_error::Push_constants_too_large{end: 80, max: 64}));
+
+    let embedded = Pipeline_reflection::new(&[reflect_embedded("vertex_shader"), reflect_embedded("fragment_shader")]).unwrap();
+    assert_eq!(embedded.check_push_constants(0), Ok(()));
+}
+
+#[test]
+fn draw_push_constants(){
+    let range = Pipeline_reflection::new(&[Shader_reflection::new(&vertex_module()).unwrap()]).unwrap().push_constants;
+    let data = (0..200).map(|byte| byte as u8).collect::<Vec<_>>();
+
+    let mut draw = Draw_command::new(Pipeline_handle(3), Vertex_buffer_handle::DEFAULT);
+    assert_eq!(draw.get_push_constants(range, &data), Err(Draw_error::Push_constant_size{pipeline: Pipeline_handle(3), expected: 80, size: 0}));
+    assert_eq!(draw.get_push_constants(None, &data), Ok(None));
+
+    draw.push_constant_size = 80;
+    draw.push_constant_offset = 100;
+    assert_eq!(draw.get_push_constants(range, &data), Ok(Some(&data[100..180])));
+    assert_eq!(draw.get_push_constants(None, &data), Err(Draw_error::Push_constant_size{pipeline: Pipeline_handle(3), expected: 0, size: 80}));
+
+    draw.push_constant_offset = 121;
+    assert_eq!(draw.get_push_constants(range, &data), Err(Draw_error::Push_constants_out_of_range{offset: 121, size: 80, available: 200}));
+    assert_eq!(draw.get_push_constants(range, &[]), Err(Draw_error::Push_constants_out_of_range{offset: 121, size: 80, available: 0}));
+}
+
+#[test]
+fn interface_mismatch(){
+    let vertex_shader = reflect_embedded("vertex_shader");
+    let mut fragment_shader = reflect_embedded("fragment_shader");
+
+    fragment_shader.inputs[0].format = vk::Format::R32G32B32A32_SFLOAT;
+    assert_eq!(Pipeline_reflection::new(&[vertex_shader.clone(), fragment_shader.clone()]), Err(Reflection_error::Interface_mismatch{
+        location: 0,
+        output: Some(vk::Format::R32G32B32_SFLOAT),
+        input: vk::Format::R32G32B32A32_SFLOAT,
+    }));
+
+    fragment_shader.inputs[0].location = 1;
+    assert!(matches!(Pipeline_reflection::new(&[vertex_shader.clone(), fragment_shader.clone()]), Err(Reflection_error::Interface_mismatch{location: 1, output: None, ..})));
+
+    //Reading fewer components than are written is fine
+    fragment_shader.inputs[0] = Interface_variable{location: 0, format: vk::Format::R32G32_SFLOAT, name: None};
+    assert!(Pipeline_reflection::new(&[vertex_shader, fragment_shader]).is_ok());
+}
+
+#[test]
+fn entry_point_stage(){
+    let code = load_spirv(find_embedded_shader("fragment_shader").unwrap().code).unwrap();
+
+    assert_eq!(Shader_reflection::new_for_stage(&code, vk::ShaderStageFlags::FRAGMENT).unwrap().stage, vk::ShaderStageFlags::FRAGMENT);
+    assert_eq!(Shader_reflection::new_for_stage(&code, vk::ShaderStageFlags::VERTEX), Err(Reflection_error::Missing_stage(vk::ShaderStageFlags::VERTEX)));
+
+    //A second entry point is picked by its execution model, not its position
+    let mut code = vertex_module();
+    instruction(&mut code, 15, &[4, 99, u32::from_le_bytes(*b"frag"), 0]); //OpEntryPoint Fragment
+    let reflection = Shader_reflection::new_for_stage(&code, vk::ShaderStageFlags::FRAGMENT).unwrap();
+    assert_eq!(reflection.entry_point, "frag");
+    assert_eq!(reflection.stage, vk::ShaderStageFlags::FRAGMENT);
+    assert!(reflection.inputs.is_empty());
+    assert_eq!(Shader_reflection::new(&code).unwrap().entry_point, "main");
+}
+
+#[test]
+fn malformed_module(){
+    let mut code = vertex_module();
+    code.push(5 << 16); //Claims more words than are left
+
+    assert!(matches!(Shader_reflection::new(&code), Err(Reflection_error::Malformed{..})));
+    assert_eq!(Shader_reflection::new(&code[..5]), Err(Reflection_error::Missing_entry_point));
+}
@@ -0,0 +1,70 @@
+//Made by Han_feng
+
+use std::fs;
+use std::path::PathBuf;
+use rust_vulkan::{find_embedded_shader, load_spirv, Shader_error, Shader_hash, Shader_store, Shader_store_error, SHADER_HASH_PREFIX};
+
+fn store_path(name: &str) -> PathBuf{
+    let path = std::env::temp_dir().join(format!("rust_vulkan_shader_store_{}_{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&path);
+    path
+}
+
+fn vertex_shader() -> &'static [u8]{
+    find_embedded_shader("vertex_shader").unwrap().code
+}
+
+#[test]
+fn hash_text(){
+    let hash = Shader_hash::new(&load_spirv(vertex_shader()).unwrap());
+    let text = hash.to_string();
+
+    assert_eq!(text.len(), 64);
+    assert_eq!(Shader_hash::parse(&text), Ok(hash));
+    assert_eq!(Shader_hash::parse(&format!("{}{}", SHADER_HASH_PREFIX, text)), Ok(hash));
+    assert!(matches!(Shader_hash::parse(&text[1..]), Err(Shader_store_error::Invalid_hash(_))));
+    assert!(matches!(Shader_hash::parse(&format!("g{}", &text[1..])), Err(Shader_store_error::Invalid_hash(_))));
+}
+
+#[test]
+fn insert_and_load(){
+    let path = store_path("insert_and_load");
+    let store = Shader_store::open(&path).unwrap();
+
+    let hash = store.insert(vertex_shader()).unwrap();
+    assert!(store.contains(&hash));
+    assert_eq!(store.load(&hash).unwrap(), load_spirv(vertex_shader()).unwrap());
+
+    //A byte swapped copy is the same module
+    let mut swapped = vertex_shader().to_vec();
+    swapped.chunks_exact_mut(4).for_each(|word| word.reverse());
+    assert_eq!(store.insert(&swapped), Ok(hash));
+
+    assert_eq!(store.insert(&[0; 8]), Err(Shader_store_error::Invalid_module(Shader_error::Truncated{size: 8})));
+
+    fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn verification(){
+    let path = store_path("verification");
+    let store = Shader_store::open(&path).unwrap();
+    let hash = store.insert(vertex_shader()).unwrap();
+
+    let missing = Shader_hash([0; 32]);
+    assert_eq!(store.load(&missing), Err(Shader_store_error::Missing(missing)));
+
+    //Flip a byte past the header so the module still parses but no longer matches its name
+    let mut data = fs::read(store.get_path(&hash)).unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 0xff;
+    fs::write(store.get_path(&hash), &data).unwrap();
+
+    assert!(matches!(store.load(&hash), Err(Shader_store_error::Hash_mismatch{expected, ..}) if expected == hash));
+
+    //Storing the module again repairs the file
+    assert_eq!(store.insert(vertex_shader()), Ok(hash));
+    assert!(store.load(&hash).is_ok());
+
+    fs::remove_dir_all(path).unwrap();
+}
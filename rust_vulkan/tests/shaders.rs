@@ -0,0 +1,82 @@
+//Made by Han_feng
+
+use rust_vulkan::{find_embedded_shader, find_embedded_shader_variant, get_embedded_shaders, get_shader_report, load_spirv, Shader_error, Shader_stage, SPIRV_MAGIC};
+
+fn spirv_data(magic: u32, version: u32, words: usize) -> Vec<u8>{
+    let mut data = vec![];
+    data.extend_from_slice(&magic.to_le_bytes());
+    data.extend_from_slice(&version.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes()); //Generator
+    data.extend_from_slice(&8u32.to_le_bytes()); //Bound
+    data.extend_from_slice(&0u32.to_le_bytes()); //Schema
+    data.resize(data.len() + words*4, 0);
+    data
+}
+
+#[test]
+fn valid_module(){
+    let data = spirv_data(SPIRV_MAGIC, 0x00010000, 3);
+    let code = load_spirv(&data).unwrap();
+
+    assert_eq!(code.len(), 8);
+    assert_eq!(code[0], SPIRV_MAGIC);
+    assert_eq!(code[3], 8);
+}
+
+#[test]
+fn byte_swapped_module(){
+    let mut data = spirv_data(SPIRV_MAGIC, 0x00010000, 0);
+    data.chunks_exact_mut(4).for_each(|word| word.reverse());
+
+    let code = load_spirv(&data).unwrap();
+    assert_eq!(code[0], SPIRV_MAGIC);
+    assert_eq!(code[1], 0x00010000);
+}
+
+#[test]
+fn unaligned_buffer(){
+    //Offset by one byte so that the module does not start on a word boundary
+    let mut buffer = vec![0];
+    buffer.extend_from_slice(&spirv_data(SPIRV_MAGIC, 0x00010000, 1));
+
+    assert_eq!(load_spirv(&buffer[1..]).unwrap().len(), 6);
+}
+
+#[test]
+fn invalid_module(){
+    let data = spirv_data(SPIRV_MAGIC, 0x00010000, 0);
+
+    assert_eq!(load_spirv(&[]), Err(Shader_error::Truncated{size: 0}));
+    assert_eq!(load_spirv(&data[..16]), Err(Shader_error::Truncated{size: 16}));
+    assert_eq!(load_spirv(&data[..19]), Err(Shader_error::Misaligned{size: 19}));
+    assert_eq!(load_spirv(&spirv_data(0xdeadbeef, 0x00010000, 0)), Err(Shader_error::Invalid_magic(0xdeadbeef)));
+    //The instance is created for Vulkan 1.0, which only consumes SPIR-V 1.0
+    assert_eq!(load_spirv(&spirv_data(SPIRV_MAGIC, 0x00010100, 0)), Err(Shader_error::Unsupported_version{major: 1, minor: 1}));
+    assert_eq!(load_spirv(&spirv_data(SPIRV_MAGIC, 0x00010700, 0)), Err(Shader_error::Unsupported_version{major: 1, minor: 7}));
+    assert_eq!(load_spirv(&spirv_data(SPIRV_MAGIC, 0x00020000, 0)), Err(Shader_error::Unsupported_version{major: 2, minor: 0}));
+}
+
+#[test]
+fn embedded_registry(){
+    let vertex_shader = find_embedded_shader("vertex_shader").unwrap();
+    let fragment_shader = find_embedded_shader("fragment_shader").unwrap();
+
+    assert_eq!(vertex_shader.stage, Shader_stage::Vertex);
+    assert_eq!(fragment_shader.stage, Shader_stage::Fragment);
+    assert!(find_embedded_shader("missing_shader").is_none());
+    assert!(find_embedded_shader_variant("vertex_shader", &["MISSING_FEATURE"]).is_none());
+    assert!(get_embedded_shaders().iter().all(|shader| find_embedded_shader_variant(shader.name, shader.features).is_some()));
+    assert!(get_embedded_shaders().iter().all(|shader| load_spirv(shader.code).is_ok()));
+}
+
+#[test]
+fn shader_report(){
+    let report: serde_json::Value = serde_json::from_str(get_shader_report()).unwrap();
+    let shaders = report["shaders"].as_array().unwrap();
+
+    assert_eq!(shaders.len(), get_embedded_shaders().len());
+    let vertex_shader = shaders.iter().find(|shader| shader["name"] == "vertex_shader" && shader["features"].as_array().unwrap().is_empty()).unwrap();
+    assert_eq!(vertex_shader["stage"], "Vertex");
+    assert_eq!(vertex_shader["entry_points"], serde_json::json!(["main"]));
+    assert!(vertex_shader["instruction_count"].as_u64().unwrap() > 0);
+}
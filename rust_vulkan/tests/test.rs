@@ -32,6 +32,12 @@ fn logger_test(){
     test_application.run();
 }
 
+#[test]
+fn prewarm_test(){
+    let mut test_application = test_lib::Test_application::prewarmed();
+    test_application.run();
+}
+
 #[test]
 #[ignore]
 fn stdout_test(){
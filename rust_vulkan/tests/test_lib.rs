@@ -2,14 +2,14 @@
 
 #![allow(non_camel_case_types)]
 
-use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
+use raw_window_handle::{HasDisplayHandle, HasWindowHandle, RawDisplayHandle, WindowsDisplayHandle};
 use winit::application::ApplicationHandler;
 use winit::dpi::LogicalSize;
 use winit::event::WindowEvent;
 use winit::event_loop::ActiveEventLoop;
 use winit::platform::windows::EventLoopBuilderExtWindows;
 use winit::window::{WindowAttributes, WindowId};
-use rust_vulkan::{Vulkan_application, Window};
+use rust_vulkan::{Draw_command, Pipeline_handle, Vertex_buffer_handle, Vulkan_application, Vulkan_config, Vulkan_prewarm, Window};
 
 cfg_if::cfg_if! {
     if #[cfg(debug_assertions)] {
@@ -22,6 +22,7 @@ cfg_if::cfg_if! {
 pub struct Test_application{
     window: Option<winit::window::Window>,
     application: Option<Vulkan_application>,
+    prewarm: Option<Vulkan_prewarm>,
 }
 
 #[cfg(debug_assertions)]
@@ -45,7 +46,14 @@ impl ApplicationHandler for Test_application {
             };
 
             self.window = Some(window);
-            self.application = Some(Vulkan_application::new(vulkan_window, None::<String>).unwrap());
+            self.application = Some(match self.prewarm.take() {
+                Some(prewarm) => {
+                    let mut application = prewarm.wait().unwrap();
+                    application.attach_surface(vulkan_window).unwrap();
+                    application
+                },
+                None => Vulkan_application::new(vulkan_window, Vulkan_config::default()).unwrap(),
+            });
         }
     }
 
@@ -55,7 +63,7 @@ impl ApplicationHandler for Test_application {
                 event_loop.exit();
             }
             WindowEvent::RedrawRequested => {
-                self.application.as_mut().unwrap().draw_frame().unwrap();
+                self.application.as_mut().unwrap().draw_frame(&[Draw_command::new(Pipeline_handle::DEFAULT, Vertex_buffer_handle::DEFAULT)], &[]).unwrap();
                 self.window.as_mut().unwrap().request_redraw();
             }
             _ => ()
@@ -64,6 +72,15 @@ impl ApplicationHandler for Test_application {
 }
 
 impl Test_application{
+    pub fn prewarmed() -> Self {
+        let display_handle = RawDisplayHandle::Windows(WindowsDisplayHandle::new());
+
+        Test_application{
+            prewarm: Some(Vulkan_application::prewarm(Some(display_handle), Vulkan_config::default()).unwrap()),
+            ..Default::default()
+        }
+    }
+
     pub fn run(&mut self) {
         let mut event_loop_builder = winit::event_loop::EventLoopBuilder::default();
         event_loop_builder.with_any_thread(true);
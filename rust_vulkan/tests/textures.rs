@@ -0,0 +1,36 @@
+//Made by Han_feng
+
+use rust_vulkan::{decode_image, decode_image_file, Texture_error};
+
+const MEDIA_DIR: &str = "../entry/src/main/resources/base/media";
+
+#[test]
+fn decode_jpeg(){
+    let image = decode_image_file(format!("{}/1.jpg", MEDIA_DIR)).unwrap();
+
+    assert_eq!((image.width, image.height), (800, 800));
+    assert_eq!(image.pixels.len(), 800 * 800 * 4);
+}
+
+#[test]
+fn decode_png(){
+    let data = std::fs::read(format!("{}/startIcon.png", MEDIA_DIR)).unwrap();
+    let image = decode_image(&data).unwrap();
+
+    assert_eq!((image.width, image.height), (144, 144));
+    assert_eq!(image.pixels.len(), 144 * 144 * 4);
+}
+
+#[test]
+fn invalid_image(){
+    assert_eq!(decode_image(b"not an image"), Err(Texture_error::Unsupported_format));
+    assert!(matches!(decode_image_file(format!("{}/missing.jpg", MEDIA_DIR)), Err(Texture_error::Io(_))));
+
+    //A JPEG header followed by garbage
+    let mut data = std::fs::read(format!("{}/1.jpg", MEDIA_DIR)).unwrap();
+    data.truncate(64);
+    assert!(matches!(decode_image(&data), Err(Texture_error::Decode(_))));
+
+    //GIF is recognized but not decoded
+    assert_eq!(decode_image(b"GIF89a\x01\x00\x01\x00"), Err(Texture_error::Unsupported_format));
+}
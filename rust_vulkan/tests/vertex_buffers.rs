@@ -0,0 +1,113 @@
+//Made by Han_feng
+
+use rust_vulkan::{check_vertex_input, find_embedded_shader, load_spirv, Colored_vertex, Descriptor_set_handle, Draw_command, Index, Index_buffer_handle, Index_type, Interface_variable, Pipeline_handle, Pipeline_manifest, Raw_vertex_attribute, Shader_reflection, Status_code, Vertex, Vertex_attribute, Vertex_buffer_handle, Vertex_format, Vertex_layout, Vertex_layout_error, MAX_DRAW_DESCRIPTOR_SETS};
+
+#[test]
+fn colored_vertex_layout(){
+    let layout = Colored_vertex::get_layout();
+
+    assert_eq!(layout, Vertex_layout{
+        stride: 20,
+        attributes: vec![
+            Vertex_attribute{location: 0, format: Vertex_format::Vec2, offset: 0},
+            Vertex_attribute{location: 1, format: Vertex_format::Vec3, offset: 8},
+        ],
+    });
+
+    //The default vertex shader is fed by it
+    let vertex_shader = Shader_reflection::new(&load_spirv(find_embedded_shader("vertex_shader").unwrap().code).unwrap()).unwrap();
+    assert_eq!(layout.check(&vertex_shader.inputs), Ok(()));
+
+    let mut partial = layout.clone();
+    partial.attributes.pop();
+    assert_eq!(partial.check(&vertex_shader.inputs), Err(Vertex_layout_error::Missing_location(1)));
+}
+
+#[test]
+fn invalid_layout(){
+    let attribute = |location, format, offset| Vertex_attribute{location, format, offset};
+
+    assert_eq!(Vertex_layout{stride: 16, attributes: vec![]}.validate(), Err(Vertex_layout_error::Empty));
+    assert_eq!(Vertex_layout{stride: 0, attributes: vec![attribute(0, Vertex_format::Float, 0)]}.validate(), Err(Vertex_layout_error::Invalid_stride(0)));
+    assert_eq!(Vertex_layout{stride: 16, attributes: vec![attribute(0, Vertex_format::Vec2, 0), attribute(0, Vertex_format::Vec2, 8)]}.validate(), Err(Vertex_layout_error::Duplicate_location(0)));
+    assert_eq!(Vertex_layout{stride: 16, attributes: vec![attribute(0, Vertex_format::Vec2, 0), attribute(1, Vertex_format::Vec3, 8)]}.validate(), Err(Vertex_layout_error::Out_of_bounds{location: 1, offset: 8, size: 12}));
+    assert_eq!(Vertex_layout{stride: 12, attributes: vec![attribute(0, Vertex_format::Vec2, 0), attribute(1, Vertex_format::Unorm8x4, 8)]}.validate(), Ok(()));
+}
+
+#[test]
+fn manifest_layout(){
+    let manifest = Pipeline_manifest::parse(r#"{
+        "pipelines": [
+            {
+                "name": "sprite",
+                "vertex_shader": "vertex_shader",
+                "fragment_shader": "fragment_shader",
+                "vertex_layout": {
+                    "stride": 12,
+                    "attributes": [
+                        {"location": 0, "format": "vec2", "offset": 0},
+                        {"location": 1, "format": "unorm8x4", "offset": 8}
+                    ]
+                }
+            }
+        ]
+    }"#).unwrap();
+
+    assert_eq!(manifest.pipelines[0].vertex_layout, Some(Vertex_layout{
+        stride: 12,
+        attributes: vec![
+            Vertex_attribute{location: 0, format: Vertex_format::Vec2, offset: 0},
+            Vertex_attribute{location: 1, format: Vertex_format::Unorm8x4, offset: 8},
+        ],
+    }));
+}
+
+#[test]
+fn instance_layout(){
+    let attribute = |location, format, offset| Vertex_attribute{location, format, offset};
+    let input = |location, format| Interface_variable{location, format, name: None};
+
+    let vertex_layout = Colored_vertex::get_layout();
+    let instance_layout = Vertex_layout{stride: 16, attributes: vec![attribute(2, Vertex_format::Vec2, 0), attribute(3, Vertex_format::Unorm8x4, 8)]};
+    let inputs = [
+        input(0, ash::vk::Format::R32G32_SFLOAT),
+        input(1, ash::vk::Format::R32G32B32_SFLOAT),
+        input(2, ash::vk::Format::R32G32_SFLOAT),
+    ];
+
+    assert_eq!(check_vertex_input(&[&vertex_layout, &instance_layout], &inputs), Ok(()));
+    assert_eq!(check_vertex_input(&[&vertex_layout], &inputs), Err(Vertex_layout_error::Missing_location(2)));
+
+    let overlapping = Vertex_layout{stride: 8, attributes: vec![attribute(1, Vertex_format::Vec2, 0)]};
+    assert_eq!(check_vertex_input(&[&vertex_layout, &overlapping], &inputs), Err(Vertex_layout_error::Duplicate_location(1)));
+}
+
+#[test]
+fn index_types(){
+    assert_eq!(<u16 as Index>::TYPE, Index_type::U16);
+    assert_eq!(<u32 as Index>::TYPE, Index_type::U32);
+    assert_eq!(Index_type::U16.get_size(), 2);
+    assert_eq!(Index_type::U32.get_size(), 4);
+}
+
+#[test]
+fn draw_command_defaults(){
+    let command = Draw_command::new(Pipeline_handle::DEFAULT, Vertex_buffer_handle::DEFAULT);
+
+    assert_eq!(command.index_buffer, Index_buffer_handle::NONE);
+    assert_eq!(command.instance_buffer, Vertex_buffer_handle::NONE);
+    assert_eq!(command.descriptor_sets, [Descriptor_set_handle::NONE; MAX_DRAW_DESCRIPTOR_SETS]);
+    assert_eq!((command.first, command.count, command.instance_count), (0, 0, 0));
+    assert_eq!((command.push_constant_offset, command.push_constant_size), (0, 0));
+}
+
+#[test]
+fn raw_values(){
+    assert!(matches!(Index_type::try_from(1), Ok(Index_type::U32)));
+    assert!(matches!(Index_type::try_from(2), Err(Status_code::Failure)));
+
+    assert!(matches!(Vertex_format::try_from(12), Ok(Vertex_format::Unorm8x4)));
+    assert!(matches!(Vertex_format::try_from(13), Err(Status_code::Failure)));
+    assert!(matches!(Vertex_attribute::try_from(Raw_vertex_attribute{location: 1, format: 2, offset: 8}), Ok(Vertex_attribute{location: 1, format: Vertex_format::Vec3, offset: 8})));
+    assert!(matches!(Vertex_attribute::try_from(Raw_vertex_attribute{location: 1, format: u32::MAX, offset: 8}), Err(Status_code::Failure)));
+}